@@ -50,8 +50,8 @@
             postInstall = ''
               cp -r assets $out/bin/
             '';
-            # Disables dynamic linking when building with Nix
-            cargoBuildOptions = x: x ++ [ "--no-default-features" ];
+            # Disables dynamic linking when building with Nix, but still builds the game
+            cargoBuildOptions = x: x ++ [ "--no-default-features" "--features" "game" ];
           };
         };
 
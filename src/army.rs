@@ -0,0 +1,162 @@
+use serde::Deserialize;
+
+use crate::status::Status;
+use crate::targeting::Targeting;
+
+/// `value * times / per`, rounded down.
+#[derive(Deserialize, Default, Clone, Copy, Debug)]
+pub struct Scale {
+    pub times: usize,
+    pub per: usize,
+}
+
+impl Scale {
+    pub fn apply(&self, value: usize) -> usize {
+        value * self.times / self.per.max(1)
+    }
+}
+
+/// How the armies of a wave get stronger with difficulty and with the bounties on the party.
+///
+/// Read from the `armies.ron` asset file. A wave has `1 + amount(difficulty)` armies picked from
+/// the `enemies` roster. Each enemy scales its health, damage and countdown from the wave's
+/// `base_health + health(difficulty)` health, `base_damage + damage(difficulty)` damage and
+/// countdown of `base_countdown - countdown(difficulty)`, but at least one tick.
+#[derive(Deserialize, Clone, Debug)]
+pub struct ArmyScaling {
+    pub base_health: usize,
+    pub base_damage: usize,
+    pub base_countdown: usize,
+    pub health: Scale,
+    pub damage: Scale,
+    pub amount: Scale,
+    pub countdown: Scale,
+    /// Every round divisible by this, the bounties on the party make the wave stronger.
+    pub penalty_every: usize,
+    pub increased_health: Scale,
+    pub increased_damage: Scale,
+    pub increased_amount: Scale,
+    pub reduced_countdown: Scale,
+    pub enemies: Vec<Enemy>,
+    pub elites: EliteScaling,
+    pub boss: BossScaling,
+}
+
+/// A kind of army the waves are made of.
+#[derive(Deserialize, Clone, Debug)]
+pub struct Enemy {
+    pub name: String,
+    pub sprite: String,
+    pub behaviour: Behaviour,
+    /// Health, damage and countdown, scaled from what the wave's armies have.
+    pub health: Scale,
+    pub damage: Scale,
+    pub countdown: Scale,
+    /// The first round it can show up in.
+    pub from_round: usize,
+    /// How likely it is to be picked for a wave, compared to the other enemies.
+    pub weight: usize,
+    /// The status it gives the minions it attacks.
+    #[serde(default)]
+    pub inflicts: Option<Status>,
+    /// Which minion it attacks, or which army it heals.
+    #[serde(default)]
+    pub targeting: Targeting,
+}
+
+/// What an army does when its countdown runs out.
+#[derive(Deserialize, Clone, PartialEq, Eq, Debug)]
+pub enum Behaviour {
+    /// Attacks a minion in the front line.
+    Soldier,
+    /// Attacks a minion, shooting over the front line.
+    Archer,
+    /// Heals another hurt army by its damage instead of attacking.
+    Shaman,
+    /// Attacks a minion in the front line, and takes `shield` less damage from every hit.
+    Brute { shield: usize },
+    /// Calls in another army instead of attacking.
+    Summoner { summons: String },
+}
+
+/// How likely armies are to be elites, which have affixes that make them tougher. The boss and the
+/// armies called in during a fight are never elites.
+#[derive(Deserialize, Default, Clone, Debug)]
+pub struct EliteScaling {
+    /// The chance out of 100 that an army is an elite, scaled from the round.
+    pub chance: Scale,
+    /// How many affixes an elite has, scaled from the round, but at least one.
+    pub affixes_each: Scale,
+    pub affixes: Vec<Affix>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct Affix {
+    pub kind: AffixKind,
+    pub amount: usize,
+    /// Shown on the armies that have it.
+    pub icon: String,
+    /// The first round elites can have it.
+    pub from_round: usize,
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AffixKind {
+    /// Heals by `amount` percent of the damage its attacks deal.
+    Vampiric,
+    /// Deals `amount` damage back to every minion that hits it.
+    Thorns,
+    /// Its countdown is `amount` ticks shorter, down to a single tick.
+    Hasted,
+    /// Splits into `amount` armies with half its max health when it dies.
+    Splitting,
+    /// Heals `amount` every tick.
+    Regenerating,
+}
+
+/// The boss that leads the wave on the rounds where the bounties on the party count. The rest of
+/// the wave are its escorts, one fewer than the armies a regular wave would have.
+#[derive(Deserialize, Clone, Debug)]
+pub struct BossScaling {
+    pub name: String,
+    pub sprite: String,
+    /// Health and damage, scaled from what a regular army in the same wave has.
+    pub health: Scale,
+    pub damage: Scale,
+    pub phases: Vec<BossPhase>,
+    /// Extra power for the draft after the boss is beaten.
+    pub reward: usize,
+}
+
+/// Something the boss starts doing once it is down to `below_percent` of its health.
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub struct BossPhase {
+    pub below_percent: usize,
+    pub ability: BossAbility,
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BossAbility {
+    /// Its attacks hit every minion instead of one.
+    Cleave,
+    /// Its countdown gets shorter by this much, down to a single tick.
+    Enrage(usize),
+    /// Calls in this many regular armies.
+    Reinforce(usize),
+}
+
+impl ArmyScaling {
+    /// Makes sure every wave can be filled and every summoner calls in an enemy that exists.
+    pub fn check(&self) -> Result<(), String> {
+        if !self.enemies.iter().any(|enemy| enemy.from_round <= 1 && enemy.weight > 0) {
+            return Err("there are no enemies for the first round".into());
+        }
+        for enemy in self.enemies.iter() {
+            let Behaviour::Summoner { summons } = &enemy.behaviour else { continue; };
+            if !self.enemies.iter().any(|e| &e.name == summons) {
+                return Err(format!("{} summons {summons}, which is not an enemy", enemy.name));
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,83 @@
+//! The balance files as Bevy assets, and the data read from them as resources. The rules in the
+//! library don't know about Bevy, so their types are wrapped here.
+
+use std::marker::PhantomData;
+
+use bevy::asset::{AssetLoader, AsyncReadExt, LoadContext, io::Reader, ron};
+use bevy::prelude::*;
+use bevy::utils::BoxedFuture;
+use serde::Deserialize;
+
+use you_behind_it_must_leave::army::ArmyScaling;
+use you_behind_it_must_leave::class::ClassList;
+use you_behind_it_must_leave::shop::Shop;
+use you_behind_it_must_leave::synergy::Synergies;
+
+/// The `classes.ron` file, before it is checked and made into `Classes`.
+#[derive(Asset, TypePath, Deserialize, Deref)]
+#[serde(transparent)]
+pub struct ClassFile(pub ClassList);
+
+/// The `armies.ron` file, before it is checked.
+#[derive(Asset, TypePath, Deserialize, Deref)]
+#[serde(transparent)]
+pub struct ArmyFile(pub ArmyScaling);
+
+/// The `synergies.ron` file.
+#[derive(Asset, TypePath, Deserialize, Deref)]
+#[serde(transparent)]
+pub struct SynergyFile(pub Synergies);
+
+/// The `shop.ron` file.
+#[derive(Asset, TypePath, Deserialize, Deref)]
+#[serde(transparent)]
+pub struct ShopFile(pub Shop);
+
+/// Balance data the game is playing with, e.g. `Balance<Classes>`.
+#[derive(Resource, Deref)]
+pub struct Balance<T>(pub T);
+
+/// Loads an asset that is written out as RON. All the balance files are plain `.ron` files, so
+/// Bevy picks this loader by the type of asset asked for rather than by extension.
+pub struct RonLoader<A> {
+    marker: PhantomData<fn() -> A>,
+}
+
+impl<A> Default for RonLoader<A> {
+    fn default() -> RonLoader<A> {
+        RonLoader {
+            marker: PhantomData,
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum RonLoaderError {
+    #[error("could not read asset: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not parse asset: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+}
+
+impl<A: Asset + for<'de> Deserialize<'de>> AssetLoader for RonLoader<A> {
+    type Asset = A;
+    type Settings = ();
+    type Error = RonLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<A, RonLoaderError>> {
+        Box::pin(async move {
+            let mut bytes = vec![];
+            reader.read_to_end(&mut bytes).await?;
+            Ok(ron::de::from_bytes(&bytes)?)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &[]
+    }
+}
@@ -0,0 +1,1124 @@
+use rand::{Rng, SeedableRng};
+use rand::seq::SliceRandom;
+use rand_chacha::ChaCha12Rng;
+
+use crate::army::{Affix, AffixKind, ArmyScaling, Behaviour, BossAbility, BossPhase, EliteScaling, Enemy};
+use crate::class::{MinionType, Trigger};
+use crate::class::Ability::*;
+use crate::class::Penalty::*;
+use crate::item::{ItemEffect, Relic};
+use crate::status::{Status, StatusKind, Statuses};
+use crate::synergy::{Bonuses, Synergies};
+use crate::targeting::{Candidate, Targeting};
+
+pub type UnitId = u64;
+
+/// How many times triggered abilities can set each other off in a single tick.
+const TRIGGER_CHAIN: usize = 8;
+
+/// How many units stand in the front line on each side, the first ones in party or wave order.
+/// Whoever is behind them is in the back line, and only stepped up as the front line falls.
+pub const FRONT_LINE: usize = 3;
+
+#[derive(Clone)]
+pub struct BattleMinion {
+    pub id: UnitId,
+    pub kind: MinionType,
+    pub health: usize,
+    pub countdown: usize,
+    pub statuses: Statuses,
+}
+
+#[derive(Clone)]
+pub struct BattleArmy {
+    pub id: UnitId,
+    pub name: String,
+    pub sprite: String,
+    pub behaviour: Behaviour,
+    pub health: usize,
+    pub max_health: usize,
+    pub damage: usize,
+    pub countdown: usize,
+    pub max_countdown: usize,
+    /// Empty unless it is an elite.
+    pub affixes: Vec<Affix>,
+    /// What it gives the minions it attacks.
+    pub inflicts: Option<Status>,
+    /// Which minion it attacks, or which army it heals.
+    pub targeting: Targeting,
+    pub statuses: Statuses,
+}
+
+impl BattleArmy {
+    /// How much less damage it takes from every hit.
+    pub fn shield(&self) -> usize {
+        match self.behaviour {
+            Behaviour::Brute { shield } => shield,
+            _ => 0,
+        }
+    }
+
+    /// The amount of the affix, if it has it.
+    pub fn affix(&self, kind: AffixKind) -> Option<usize> {
+        self.affixes.iter().find(|affix| affix.kind == kind).map(|affix| affix.amount)
+    }
+}
+
+/// What a minion has done over the course of the fight, kept around after it dies.
+#[derive(Clone)]
+pub struct Tally {
+    pub id: UnitId,
+    pub class: String,
+    /// The minion that summoned this one, if any.
+    pub summoned_by: Option<UnitId>,
+    pub damage_dealt: usize,
+    pub healing_done: usize,
+}
+
+/// The boss of a boss round and how far it has got through its phases.
+#[derive(Clone)]
+pub struct BossFight {
+    pub id: UnitId,
+    phases: Vec<BossPhase>,
+    /// The number of phases it has entered so far.
+    pub phase: usize,
+    cleaves: bool,
+}
+
+/// Health, damage and countdown of the wave's armies, before each enemy scales them.
+#[derive(Clone, Copy, Default)]
+struct Wave {
+    health: usize,
+    damage: usize,
+    countdown: usize,
+}
+
+/// Something that happened during a tick, for whatever wants to react to it without being wired
+/// into the fight itself.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CombatEvent {
+    /// `target` lost `amount` health to `source`.
+    DamageDealt { source: UnitId, target: UnitId, amount: usize },
+    /// `target` gained `amount` health from `source`, which can be itself.
+    Healed { source: UnitId, target: UnitId, amount: usize },
+    /// `unit` was removed from the fight, after the damage that did it.
+    UnitDied { unit: UnitId, killer: UnitId },
+    /// `unit` joined the fight, summoned or called in by `summoner`.
+    UnitSummoned { summoner: UnitId, unit: UnitId },
+    /// `source` changed the countdown of `target` to `countdown`. Countdowns running down with
+    /// every tick don't count.
+    CountdownChanged { source: UnitId, target: UnitId, countdown: usize },
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Outcome {
+    Ongoing,
+    Victory,
+    Defeat,
+}
+
+/// A single fight between the party and a wave of armies, independent of the ECS.
+///
+/// `fight_tick` steps one of these and mirrors the result onto the spawned entities, but it can
+/// just as well be stepped on its own to simulate a fight.
+pub struct Battle {
+    pub minions: Vec<BattleMinion>,
+    pub armies: Vec<BattleArmy>,
+    pub tallies: Vec<Tally>,
+    /// The number of ticks stepped so far.
+    pub ticks: usize,
+    /// What the party's synergies add to every minion, including the ones summoned later.
+    pub bonuses: Bonuses,
+    /// The boss, if this is a boss round. Kept around after it is beaten.
+    pub boss: Option<BossFight>,
+    /// The max health each minion gained with `Grow`, for the party to keep once the fight is won.
+    pub grown: Vec<(UnitId, usize)>,
+    /// The enemies armies can be called in from, and the round that decides which of them can.
+    roster: Vec<Enemy>,
+    elites: EliteScaling,
+    round: usize,
+    wave: Wave,
+    /// The same generator as the run's, so that fights replay the same on every build.
+    rng: ChaCha12Rng,
+    next_id: UnitId,
+    events: Vec<CombatEvent>,
+}
+
+/// An army attacking a minion.
+#[derive(Clone, Copy)]
+struct Attack {
+    army: UnitId,
+    damage: usize,
+    targeting: Targeting,
+    /// Whether it can hit minions in the back line.
+    reach: bool,
+}
+
+/// Effects that are queued up while minions act and resolved once everyone has had their turn.
+#[derive(Default)]
+struct Effects {
+    army_attacks: Vec<Attack>,
+    army_cleaves: Vec<(UnitId, usize)>,
+    army_heals: Vec<(UnitId, usize)>,
+    army_summons: Vec<(UnitId, String)>,
+    /// Armies that died with the Splitting affix.
+    splits: Vec<BattleArmy>,
+    /// Armies with the Thorns affix, the minion that hit them and how much damage comes back at it.
+    thorns: Vec<(UnitId, UnitId, usize)>,
+    heals: Vec<(UnitId, usize, Targeting)>,
+    aoe_heals: Vec<(UnitId, usize)>,
+    aoe_overheals: Vec<(UnitId, usize)>,
+    reductions: Vec<(UnitId, usize, Targeting)>,
+    aoe_reductions: Vec<(UnitId, usize)>,
+    summons: Vec<(UnitId, MinionType)>,
+}
+
+impl Battle {
+    pub fn new(seed: u64) -> Battle {
+        Battle {
+            minions: vec![],
+            armies: vec![],
+            tallies: vec![],
+            ticks: 0,
+            bonuses: Bonuses::default(),
+            boss: None,
+            grown: vec![],
+            roster: vec![],
+            elites: EliteScaling::default(),
+            round: 0,
+            wave: Wave::default(),
+            rng: ChaCha12Rng::seed_from_u64(seed),
+            next_id: 0,
+            events: vec![],
+        }
+    }
+
+    /// Sets up the fight for the given round: the whole party against a wave scaled by
+    /// `difficulty`. Every few rounds the party's bounties make the wave stronger and a boss leads
+    /// it. The party's synergies and relics apply for the whole fight, and the wave's armies may
+    /// be elites.
+    pub fn start(
+        difficulty: usize,
+        party: &[(MinionType, usize)],
+        scaling: &ArmyScaling,
+        synergies: &Synergies,
+        relics: &[Relic],
+        seed: u64,
+    ) -> Battle {
+        let mut battle = Battle::new(seed);
+        battle.bonuses = synergies.bonuses(party);
+        for relic in relics {
+            battle.bonuses.add(relic.bonus, relic.amount);
+        }
+        let mut health = scaling.health.apply(difficulty);
+        let mut amount = scaling.amount.apply(difficulty);
+        let mut countdown = scaling.countdown.apply(difficulty);
+        let mut damage = scaling.damage.apply(difficulty);
+
+        let boss_round = difficulty.is_multiple_of(scaling.penalty_every.max(1));
+        for (minion, bounty) in party.iter() {
+            battle.add_minion(minion);
+            if boss_round {
+                for penalty in &minion.penalties {
+                    match penalty {
+                        IncreasedAmount => amount += scaling.increased_amount.apply(*bounty),
+                        IncreasedDamage => damage += scaling.increased_damage.apply(*bounty),
+                        IncreasedHealth => health += scaling.increased_health.apply(*bounty),
+                        ReducedCountdown => countdown += scaling.reduced_countdown.apply(*bounty),
+                    }
+                }
+            }
+        }
+
+        battle.roster = scaling.enemies.clone();
+        battle.elites = scaling.elites.clone();
+        battle.round = difficulty;
+        battle.wave = Wave {
+            health: scaling.base_health + health,
+            damage: scaling.base_damage + damage,
+            countdown: scaling.base_countdown.saturating_sub(countdown).max(1),
+        };
+        if boss_round {
+            let boss = &scaling.boss;
+            let wave = battle.wave;
+            let id = battle.add_army(
+                &boss.name,
+                &boss.sprite,
+                Behaviour::Soldier,
+                boss.health.apply(wave.health).max(1),
+                boss.damage.apply(wave.damage),
+                wave.countdown,
+            );
+            battle.boss = Some(BossFight {
+                id,
+                phases: boss.phases.clone(),
+                phase: 0,
+                cleaves: false,
+            });
+        } else if let Some(id) = battle.call_in_any() {
+            battle.roll_elite(id);
+        }
+        for _ in 0..amount {
+            if let Some(id) = battle.call_in_any() {
+                battle.roll_elite(id);
+            }
+        }
+
+        battle
+    }
+
+    /// Adds a minion to the party, with the health and countdown bonuses and its item applied to
+    /// its kind.
+    pub fn add_minion(&mut self, kind: &MinionType) -> UnitId {
+        let id = self.next_id();
+        let mut kind = kind.clone();
+        if let Some(effect) = kind.item.as_ref().map(|item| item.effect) {
+            effect.apply(&mut kind);
+        }
+        kind.max_health += self.bonuses.health;
+        kind.max_countdown = kind.max_countdown.saturating_sub(self.bonuses.countdown).max(1);
+        kind.start_countdown = kind.start_countdown.saturating_sub(self.bonuses.countdown);
+        self.tallies.push(Tally {
+            id,
+            class: kind.name.clone(),
+            summoned_by: None,
+            damage_dealt: 0,
+            healing_done: 0,
+        });
+        self.minions.push(BattleMinion {
+            id,
+            health: kind.max_health,
+            countdown: kind.start_countdown,
+            kind,
+            statuses: Statuses::default(),
+        });
+        id
+    }
+
+    pub fn add_army(
+        &mut self,
+        name: &str,
+        sprite: &str,
+        behaviour: Behaviour,
+        health: usize,
+        damage: usize,
+        countdown: usize,
+    ) -> UnitId {
+        let id = self.next_id();
+        self.armies.push(BattleArmy {
+            id,
+            name: name.into(),
+            sprite: sprite.into(),
+            behaviour,
+            max_health: health,
+            health,
+            damage,
+            countdown,
+            max_countdown: countdown,
+            affixes: vec![],
+            inflicts: None,
+            targeting: Targeting::Random,
+            statuses: Statuses::default(),
+        });
+        id
+    }
+
+    /// Adds an army of the given enemy, scaled for this wave.
+    pub fn call_in(&mut self, enemy: &Enemy) -> UnitId {
+        let wave = self.wave;
+        let id = self.add_army(
+            &enemy.name,
+            &enemy.sprite,
+            enemy.behaviour.clone(),
+            enemy.health.apply(wave.health).max(1),
+            enemy.damage.apply(wave.damage),
+            enemy.countdown.apply(wave.countdown).max(1),
+        );
+        let army = self.armies.last_mut().unwrap();
+        army.inflicts = enemy.inflicts;
+        army.targeting = enemy.targeting;
+        id
+    }
+
+    /// Adds an army of an enemy picked by weight from the ones that can show up this round.
+    fn call_in_any(&mut self) -> Option<UnitId> {
+        let round = self.round;
+        let available: Vec<_> = self.roster.iter()
+            .filter(|enemy| enemy.from_round <= round && enemy.weight > 0)
+            .cloned()
+            .collect();
+        let total: usize = available.iter().map(|enemy| enemy.weight).sum();
+        if total == 0 {
+            return None;
+        }
+        let mut pick = self.rng.gen_range(0..total);
+        for enemy in available.iter() {
+            if pick < enemy.weight {
+                return Some(self.call_in(enemy));
+            }
+            pick -= enemy.weight;
+        }
+        None
+    }
+
+    /// Maybe makes the army an elite, with affixes picked from the ones available this round.
+    fn roll_elite(&mut self, id: UnitId) {
+        let round = self.round;
+        let available: Vec<_> = self.elites.affixes.iter()
+            .filter(|affix| affix.from_round <= round)
+            .cloned()
+            .collect();
+        if available.is_empty() || self.rng.gen_range(0..100) >= self.elites.chance.apply(round) {
+            return;
+        }
+        let count = self.elites.affixes_each.apply(round).clamp(1, available.len());
+        let affixes: Vec<_> = available.choose_multiple(&mut self.rng, count).cloned().collect();
+        let army = self.armies.iter_mut().find(|a| a.id == id).unwrap();
+        for affix in affixes {
+            if affix.kind == AffixKind::Hasted {
+                army.max_countdown = army.max_countdown.saturating_sub(affix.amount).max(1);
+                army.countdown = army.countdown.min(army.max_countdown);
+            }
+            army.affixes.push(affix);
+        }
+    }
+
+    /// Adds the armies a Splitting army splits into, each with half its max health and the rest of
+    /// its affixes.
+    fn split(&mut self, army: &BattleArmy) {
+        let amount = army.affix(AffixKind::Splitting).unwrap_or(0);
+        let health = (army.max_health / 2).max(1);
+        for _ in 0..amount {
+            let id = self.add_army(&army.name, &army.sprite, army.behaviour.clone(), health, army.damage, army.max_countdown);
+            let split = self.armies.iter_mut().find(|a| a.id == id).unwrap();
+            split.affixes = army.affixes.iter().filter(|affix| affix.kind != AffixKind::Splitting).cloned().collect();
+            split.inflicts = army.inflicts;
+            split.targeting = army.targeting;
+            self.emit(CombatEvent::UnitSummoned { summoner: army.id, unit: id });
+        }
+    }
+
+    pub fn minion(&self, id: UnitId) -> Option<&BattleMinion> {
+        self.minions.iter().find(|m| m.id == id)
+    }
+
+    pub fn army(&self, id: UnitId) -> Option<&BattleArmy> {
+        self.armies.iter().find(|a| a.id == id)
+    }
+
+    pub fn tally(&self, id: UnitId) -> Option<&Tally> {
+        self.tallies.iter().find(|t| t.id == id)
+    }
+
+    /// The minion that gets the credit for what `id` does: whoever summoned it, all the way up.
+    pub fn credited_to(&self, id: UnitId) -> UnitId {
+        match self.tally(id).and_then(|tally| tally.summoned_by) {
+            Some(summoner) => self.credited_to(summoner),
+            None => id,
+        }
+    }
+
+    fn tally_mut(&mut self, id: UnitId) -> &mut Tally {
+        self.tallies.iter_mut().find(|t| t.id == id).unwrap()
+    }
+
+    /// Everything that has happened since the last time this was called, in order.
+    pub fn take_events(&mut self) -> Vec<CombatEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// Records what happened, adding damage and healing done by minions to their tallies. Damage
+    /// and healing that didn't change anything are left out.
+    fn emit(&mut self, event: CombatEvent) {
+        match event {
+            CombatEvent::DamageDealt { amount: 0, .. } | CombatEvent::Healed { amount: 0, .. } => return,
+            CombatEvent::DamageDealt { source, amount, .. } => {
+                if let Some(tally) = self.tallies.iter_mut().find(|t| t.id == source) {
+                    tally.damage_dealt += amount;
+                }
+            }
+            CombatEvent::Healed { source, amount, .. } => {
+                if let Some(tally) = self.tallies.iter_mut().find(|t| t.id == source) {
+                    tally.healing_done += amount;
+                }
+            }
+            _ => {}
+        }
+        self.events.push(event);
+    }
+
+    fn next_id(&mut self) -> UnitId {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    /// Advances the fight by one tick.
+    ///
+    /// Statuses deal their damage first. Armies regenerate and count down next, then
+    /// every minion counts down and acts in party order, and armies that split on death split.
+    /// Stunned units don't count down. Heals, countdown reductions and whatever the
+    /// armies do are resolved after that, in that order, and then the boss enters any phases it is
+    /// hurt enough for. Abilities triggered by all that go next, and are resolved the same way.
+    /// Statuses count down last, except the ones given during the tick. The tick ends early once
+    /// the last army has fallen.
+    pub fn step(&mut self) -> Outcome {
+        if self.armies.is_empty() {
+            return Outcome::Victory;
+        }
+        if self.minions.is_empty() {
+            return Outcome::Defeat;
+        }
+
+        let seen = self.events.len();
+        if self.ticks == 0 {
+            let mut effects = Effects::default();
+            let party: Vec<_> = self.minions.iter().map(|m| m.id).collect();
+            for id in party {
+                self.trigger(id, Trigger::FightStart, None, &mut effects);
+            }
+            self.settle(effects);
+        }
+
+        self.ticks += 1;
+        let mut effects = Effects::default();
+        self.tick_statuses(&mut effects);
+        if self.armies.is_empty() || self.minions.is_empty() {
+            self.settle(effects);
+            return self.finish_tick(seen);
+        }
+
+        let cleaver = self.boss.as_ref().filter(|boss| boss.cleaves).map(|boss| boss.id);
+        let mut regenerated = vec![];
+        for army in self.armies.iter_mut() {
+            if let Some(regeneration) = army.affix(AffixKind::Regenerating).filter(|_| !army.statuses.has(StatusKind::Poison)) {
+                let before = army.health;
+                army.health = (army.health + regeneration).min(army.max_health);
+                regenerated.push((army.id, army.health - before));
+            }
+            if army.statuses.has(StatusKind::Stun) {
+                continue;
+            }
+            army.countdown -= 1;
+            if army.countdown > 0 {
+                continue;
+            }
+            army.countdown = army.max_countdown;
+            let damage = army.damage.saturating_sub(army.statuses.get(StatusKind::Weakness).unwrap_or(0));
+            if Some(army.id) == cleaver {
+                effects.army_cleaves.push((army.id, damage));
+                continue;
+            }
+            match &army.behaviour {
+                Behaviour::Soldier | Behaviour::Brute { .. } | Behaviour::Archer => effects.army_attacks.push(Attack {
+                    army: army.id,
+                    damage,
+                    targeting: army.targeting,
+                    reach: army.behaviour == Behaviour::Archer,
+                }),
+                Behaviour::Shaman => effects.army_heals.push((army.id, army.damage)),
+                Behaviour::Summoner { summons } => effects.army_summons.push((army.id, summons.clone())),
+            }
+        }
+        for (army, amount) in regenerated {
+            self.emit(CombatEvent::Healed { source: army, target: army, amount });
+        }
+
+        for i in 0..self.minions.len() {
+            if self.armies.is_empty() {
+                break;
+            }
+            let minion = &mut self.minions[i];
+            if minion.statuses.has(StatusKind::Stun) {
+                continue;
+            }
+            if minion.countdown > 0 {
+                minion.countdown -= 1;
+            }
+            if minion.countdown > 0 {
+                continue;
+            }
+            minion.countdown = minion.kind.max_countdown;
+            let (id, kind) = (minion.id, minion.kind.clone());
+            self.act(id, &kind, None, &mut effects);
+        }
+        self.settle(effects);
+        self.finish_tick(seen)
+    }
+
+    /// Uses the abilities triggered during the tick and, if that beat the wave, the party's end of
+    /// fight abilities, then counts statuses down.
+    fn finish_tick(&mut self, seen: usize) -> Outcome {
+        self.chain_triggers(seen);
+        if self.armies.is_empty() && !self.minions.is_empty() {
+            // `Grow` is all that is allowed at the end of the fight, and it queues up nothing.
+            let mut effects = Effects::default();
+            let party: Vec<_> = self.minions.iter().map(|m| m.id).collect();
+            for id in party {
+                self.trigger(id, Trigger::FightEnd, None, &mut effects);
+            }
+        }
+        for army in self.armies.iter_mut() {
+            army.statuses.end_tick();
+        }
+        for minion in self.minions.iter_mut() {
+            minion.statuses.end_tick();
+        }
+        self.outcome()
+    }
+
+    /// How the fight stands at the end of a tick. Victory is only called at the start of the next.
+    fn outcome(&self) -> Outcome {
+        if self.minions.is_empty() {
+            Outcome::Defeat
+        } else {
+            Outcome::Ongoing
+        }
+    }
+
+    /// Puts every status into effect for the tick and deals the damage of the ones that hurt.
+    fn tick_statuses(&mut self, effects: &mut Effects) {
+        let armies: Vec<_> = self.armies.iter().map(|a| a.id).collect();
+        for id in armies {
+            let Some(index) = self.armies.iter().position(|a| a.id == id) else { continue; };
+            for (source, damage) in self.armies[index].statuses.start_tick() {
+                let Some(index) = self.armies.iter().position(|a| a.id == id) else { break; };
+                self.hurt_army(source, index, damage, effects);
+            }
+        }
+
+        let minions: Vec<_> = self.minions.iter().map(|m| m.id).collect();
+        for id in minions {
+            let Some(index) = self.minions.iter().position(|m| m.id == id) else { continue; };
+            for (source, damage) in self.minions[index].statuses.start_tick() {
+                let Some(index) = self.minions.iter().position(|m| m.id == id) else { break; };
+                self.hurt_minion(source, index, damage);
+            }
+        }
+    }
+
+    /// Resolves everything queued up while units acted.
+    fn settle(&mut self, mut effects: Effects) {
+        for army in std::mem::take(&mut effects.splits) {
+            self.split(&army);
+        }
+
+        if !self.armies.is_empty() {
+            self.resolve(&effects);
+            self.advance_boss();
+        }
+
+        for (summoner, kind) in effects.summons {
+            let id = self.add_minion(&kind);
+            self.tally_mut(id).summoned_by = Some(summoner);
+            self.emit(CombatEvent::UnitSummoned { summoner, unit: id });
+        }
+
+        // Armies that are called in once the wave is beaten come too late.
+        if !self.armies.is_empty() {
+            for (summoner, name) in effects.army_summons {
+                let Some(enemy) = self.roster.iter().find(|e| e.name == name).cloned() else { continue; };
+                let id = self.call_in(&enemy);
+                self.emit(CombatEvent::UnitSummoned { summoner, unit: id });
+            }
+        }
+    }
+
+    /// Uses the abilities triggered by the events from `seen` on, then the ones triggered by
+    /// those, until nothing more is triggered or the chain gets too long.
+    fn chain_triggers(&mut self, mut seen: usize) {
+        for _ in 0..TRIGGER_CHAIN {
+            let events = self.events[seen..].to_vec();
+            seen = self.events.len();
+            let mut effects = Effects::default();
+            let mut triggered = false;
+            for event in events {
+                match event {
+                    CombatEvent::UnitDied { unit, killer } => {
+                        if self.tally(unit).is_some() {
+                            let party: Vec<_> = self.minions.iter().map(|m| m.id).collect();
+                            for id in party {
+                                triggered |= self.trigger(id, Trigger::AllyDied, Some(unit), &mut effects);
+                            }
+                        }
+                        triggered |= self.trigger(killer, Trigger::Kill, Some(unit), &mut effects);
+                    }
+                    CombatEvent::UnitSummoned { summoner, unit } => {
+                        triggered |= self.trigger(summoner, Trigger::Summon, Some(unit), &mut effects);
+                    }
+                    _ => {}
+                }
+            }
+            if !triggered {
+                return;
+            }
+            self.settle(effects);
+        }
+    }
+
+    /// Uses the abilities of minion `id` that `trigger` sets off, if it is still alive and the
+    /// fight isn't over. `subject` is the unit that triggered them. Returns whether any were used.
+    fn trigger(&mut self, id: UnitId, trigger: Trigger, subject: Option<UnitId>, effects: &mut Effects) -> bool {
+        let Some(minion) = self.minion(id) else { return false; };
+        let mut kind = minion.kind.clone();
+        let triggered: Vec<_> = kind.triggered.iter().filter(|t| t.trigger == trigger).copied().collect();
+        let mut used = false;
+        for triggered in triggered {
+            if self.armies.is_empty() && trigger != Trigger::FightEnd {
+                break;
+            }
+            kind.ability = triggered.ability;
+            kind.amount = triggered.amount;
+            self.act(id, &kind, subject, effects);
+            used = true;
+        }
+        used
+    }
+
+    /// Uses an ability of `kind`. `subject` is the unit that triggered it, if it was triggered.
+    fn act(&mut self, id: UnitId, kind: &MinionType, subject: Option<UnitId>, effects: &mut Effects) {
+        let amount = kind.amount;
+        let weakness = self.minion(id).and_then(|m| m.statuses.get(StatusKind::Weakness)).unwrap_or(0);
+        let damage = (amount + self.bonuses.damage).saturating_sub(weakness);
+        let heal = amount + self.bonuses.healing;
+        let targets = if kind.item.as_ref().is_some_and(|item| item.effect == ItemEffect::ExtraTarget) { 2 } else { 1 };
+        match kind.ability {
+            Damage => for _ in 0..targets {
+                let Some(target) = self.army_target(kind) else { return; };
+                let army = self.armies[target].id;
+                self.hit_army(id, target, damage, effects);
+                self.afflict(id, army, kind.status);
+            }
+            AoeDamage => {
+                let armies: Vec<_> = self.armies.iter().map(|a| a.id).collect();
+                // Backwards, so that armies dying don't move the ones still to be hit.
+                for target in (0..self.armies.len()).rev() {
+                    self.hit_army(id, target, damage, effects);
+                }
+                for army in armies {
+                    self.afflict(id, army, kind.status);
+                }
+            }
+            Heal => for _ in 0..targets {
+                effects.heals.push((id, heal, kind.targeting));
+            }
+            AoeHeal => effects.aoe_heals.push((id, heal)),
+            AoeOverheal => effects.aoe_overheals.push((id, heal)),
+            Reduce => for _ in 0..targets {
+                effects.reductions.push((id, amount, kind.targeting));
+            }
+            AoeReduce => effects.aoe_reductions.push((id, amount)),
+            Delay => for _ in 0..targets {
+                let Some(target) = self.army_target(kind) else { return; };
+                let army = &mut self.armies[target];
+                army.countdown += amount;
+                let (target, countdown) = (army.id, army.countdown);
+                self.emit(CombatEvent::CountdownChanged { source: id, target, countdown });
+                self.afflict(id, target, kind.status);
+            }
+            Afflict => for _ in 0..targets {
+                let Some(target) = self.army_target(kind) else { return; };
+                let army = self.armies[target].id;
+                self.afflict(id, army, kind.status);
+            }
+            AoeGrant => {
+                let Some(status) = kind.status else { return; };
+                for minion in self.minions.iter_mut() {
+                    minion.statuses.apply(status, id);
+                }
+            }
+            Summon => {
+                let summon = kind.summon.as_deref().expect("summoning class without a summon");
+                effects.summons.push((id, summon.clone()));
+            }
+            Execute => for _ in 0..targets {
+                let Some(target) = self.army_target(kind) else { return; };
+                self.kill_army(id, target, effects);
+            }
+            Empower => {
+                let target = subject.filter(|&subject| self.minion(subject).is_some()).unwrap_or(id);
+                let Some(minion) = self.minions.iter_mut().find(|m| m.id == target) else { return; };
+                minion.kind.max_health += amount;
+                minion.health += amount;
+                self.emit(CombatEvent::Healed { source: id, target, amount });
+            }
+            Grow => {
+                let target = subject.filter(|&subject| self.minion(subject).is_some()).unwrap_or(id);
+                let Some(minion) = self.minions.iter_mut().find(|m| m.id == target) else { return; };
+                minion.kind.max_health += amount;
+                self.grown.push((target, amount));
+            }
+        }
+    }
+
+    /// Picks an army in the front line for the minion's ability, or anywhere if it can reach the
+    /// back line.
+    fn army_target(&mut self, kind: &MinionType) -> Option<usize> {
+        let line = if kind.reach { self.armies.len() } else { self.armies.len().min(FRONT_LINE) };
+        let candidates: Vec<_> = self.armies[..line].iter().enumerate()
+            .map(|(index, army)| Candidate::army(index, army))
+            .collect();
+        kind.targeting.pick(&candidates, &mut self.rng)
+    }
+
+    /// Picks a minion out of the ones `can_target` allows, given where they are and what they are.
+    fn minion_target(&mut self, targeting: Targeting, can_target: impl Fn(usize, &BattleMinion) -> bool) -> Option<usize> {
+        let candidates: Vec<_> = self.minions.iter().enumerate()
+            .filter(|&(index, minion)| can_target(index, minion))
+            .map(|(index, minion)| Candidate::minion(index, minion))
+            .collect();
+        targeting.pick(&candidates, &mut self.rng)
+    }
+
+    /// Gives an army a status, if there is one to give and it is still alive.
+    fn afflict(&mut self, source: UnitId, army: UnitId, status: Option<Status>) {
+        let Some(status) = status else { return; };
+        let Some(army) = self.armies.iter_mut().find(|a| a.id == army) else { return; };
+        army.statuses.apply(status, source);
+    }
+
+    /// Gives a minion what the army that hit it inflicts, if it is still alive.
+    fn inflict(&mut self, army: UnitId, minion: UnitId) {
+        let Some(status) = self.army(army).and_then(|a| a.inflicts) else { return; };
+        let Some(minion) = self.minions.iter_mut().find(|m| m.id == minion) else { return; };
+        minion.statuses.apply(status, army);
+    }
+
+    /// `source` deals `damage` to an army, less its shields, returning how much health it actually
+    /// lost.
+    fn hit_army(&mut self, source: UnitId, index: usize, damage: usize, effects: &mut Effects) -> usize {
+        let army = &mut self.armies[index];
+        if let Some(thorns) = army.affix(AffixKind::Thorns) {
+            effects.thorns.push((army.id, source, thorns));
+        }
+        let damage = damage.saturating_sub(army.shield());
+        let damage = army.statuses.absorb(damage);
+        let dealt = self.hurt_army(source, index, damage, effects);
+        self.lifesteal(source, dealt);
+        dealt
+    }
+
+    /// `source` takes `damage` off an army's health, whatever shields it has.
+    fn hurt_army(&mut self, source: UnitId, index: usize, damage: usize, effects: &mut Effects) -> usize {
+        let army = &mut self.armies[index];
+        if army.health <= damage {
+            return self.kill_army(source, index, effects);
+        }
+        army.health -= damage;
+        let target = army.id;
+        self.emit(CombatEvent::DamageDealt { source, target, amount: damage });
+        damage
+    }
+
+    /// `killer` takes all of an army's health, returning how much that was.
+    fn kill_army(&mut self, killer: UnitId, index: usize, effects: &mut Effects) -> usize {
+        let army = self.armies.remove(index);
+        let dealt = army.health;
+        self.emit(CombatEvent::DamageDealt { source: killer, target: army.id, amount: dealt });
+        self.emit(CombatEvent::UnitDied { unit: army.id, killer });
+        if army.affix(AffixKind::Splitting).is_some() {
+            effects.splits.push(army);
+        }
+        dealt
+    }
+
+    fn resolve(&mut self, effects: &Effects) {
+        for &(source, heal, targeting) in &effects.heals {
+            let Some(target) = self.minion_target(targeting, |_, minion| can_heal(minion)) else { continue; };
+            self.heal_minion(source, target, heal);
+        }
+
+        for &(source, heal) in &effects.aoe_heals {
+            for target in 0..self.minions.len() {
+                self.heal_minion(source, target, heal);
+            }
+        }
+
+        for &(source, heal) in &effects.aoe_overheals {
+            for target in 0..self.minions.len() {
+                let minion = &mut self.minions[target];
+                if minion.statuses.has(StatusKind::Poison) {
+                    continue;
+                }
+                minion.health += heal;
+                let target = minion.id;
+                self.emit(CombatEvent::Healed { source, target, amount: heal });
+            }
+        }
+
+        for &(source, reduction, targeting) in &effects.reductions {
+            let Some(target) = self.minion_target(targeting, |_, _| true) else { continue; };
+            self.reduce_countdown(source, target, reduction);
+        }
+
+        for &(source, reduction) in &effects.aoe_reductions {
+            for target in 0..self.minions.len() {
+                self.reduce_countdown(source, target, reduction);
+            }
+        }
+
+        for &(shaman, heal) in &effects.army_heals {
+            let targeting = self.army(shaman).map_or(Targeting::Random, |army| army.targeting);
+            let candidates: Vec<_> = self.armies.iter().enumerate()
+                .filter(|(_, army)| army.id != shaman && army.health < army.max_health)
+                .filter(|(_, army)| !army.statuses.has(StatusKind::Poison))
+                .map(|(index, army)| Candidate::army(index, army))
+                .collect();
+            if let Some(index) = targeting.pick(&candidates, &mut self.rng) {
+                let army = &mut self.armies[index];
+                let before = army.health;
+                army.health = (army.health + heal).min(army.max_health);
+                let event = CombatEvent::Healed { source: shaman, target: army.id, amount: army.health - before };
+                self.emit(event);
+            }
+        }
+
+        for &(army, minion, thorns) in &effects.thorns {
+            let Some(target) = self.minions.iter().position(|m| m.id == minion) else { continue; };
+            self.hit_minion(army, target, thorns);
+        }
+
+        for &Attack { army, damage, targeting, reach } in &effects.army_attacks {
+            let line = if reach { self.minions.len() } else { FRONT_LINE };
+            // Taunting minions draw every attack while they live, wherever they stand.
+            let taunted = self.minions.iter().any(|m| m.kind.taunt);
+            let Some(target) = self.minion_target(targeting, |index, minion| {
+                if taunted { minion.kind.taunt } else { index < line }
+            }) else { break; };
+            let minion = self.minions[target].id;
+            let dealt = self.hit_minion(army, target, damage);
+            self.drain(army, dealt);
+            self.inflict(army, minion);
+        }
+
+        for &(army, attack) in &effects.army_cleaves {
+            let minions: Vec<_> = self.minions.iter().map(|m| m.id).collect();
+            let mut dealt = 0;
+            // Backwards, so that minions dying don't move the ones still to be hit.
+            for target in (0..self.minions.len()).rev() {
+                dealt += self.hit_minion(army, target, attack);
+            }
+            self.drain(army, dealt);
+            for minion in minions {
+                self.inflict(army, minion);
+            }
+        }
+    }
+
+    /// Heals a minion up to max health, leaving it alone if it is overhealed or poisoned.
+    fn heal_minion(&mut self, source: UnitId, index: usize, heal: usize) {
+        let minion = &mut self.minions[index];
+        if minion.health > minion.kind.max_health || minion.statuses.has(StatusKind::Poison) {
+            return;
+        }
+        let before = minion.health;
+        minion.health = (minion.health + heal).min(minion.kind.max_health);
+        let event = CombatEvent::Healed { source, target: minion.id, amount: minion.health - before };
+        self.emit(event);
+    }
+
+    /// Takes `reduction` off a minion's countdown, unless that would take it below zero.
+    fn reduce_countdown(&mut self, source: UnitId, index: usize, reduction: usize) {
+        let minion = &mut self.minions[index];
+        if minion.countdown < reduction {
+            return;
+        }
+        minion.countdown -= reduction;
+        let event = CombatEvent::CountdownChanged { source, target: minion.id, countdown: minion.countdown };
+        self.emit(event);
+    }
+
+    /// `source` deals `attack` to a minion, less its shield, returning how much health it actually
+    /// lost.
+    fn hit_minion(&mut self, source: UnitId, index: usize, attack: usize) -> usize {
+        let attack = self.minions[index].statuses.absorb(attack);
+        self.hurt_minion(source, index, attack)
+    }
+
+    /// `source` takes `attack` off a minion's health, whatever shield it has.
+    fn hurt_minion(&mut self, source: UnitId, index: usize, attack: usize) -> usize {
+        let minion = &mut self.minions[index];
+        let target = minion.id;
+        if minion.health > attack {
+            minion.health -= attack;
+            self.emit(CombatEvent::DamageDealt { source, target, amount: attack });
+            return attack;
+        }
+        let dealt = self.minions.remove(index).health;
+        self.emit(CombatEvent::DamageDealt { source, target, amount: dealt });
+        self.emit(CombatEvent::UnitDied { unit: target, killer: source });
+        dealt
+    }
+
+    /// Heals a Vampiric army by its share of the damage it dealt.
+    fn drain(&mut self, id: UnitId, dealt: usize) {
+        let Some(army) = self.armies.iter_mut().find(|a| a.id == id) else { return; };
+        let Some(percent) = army.affix(AffixKind::Vampiric) else { return; };
+        if army.statuses.has(StatusKind::Poison) {
+            return;
+        }
+        let before = army.health;
+        army.health = (army.health + dealt * percent / 100).min(army.max_health);
+        let amount = army.health - before;
+        self.emit(CombatEvent::Healed { source: id, target: id, amount });
+    }
+
+    /// Heals a minion carrying a lifesteal item by its share of the damage it dealt.
+    fn lifesteal(&mut self, id: UnitId, dealt: usize) {
+        let Some(index) = self.minions.iter().position(|m| m.id == id) else { return; };
+        let Some(ItemEffect::Lifesteal(percent)) = self.minions[index].kind.item.as_ref().map(|item| item.effect) else {
+            return;
+        };
+        self.heal_minion(id, index, dealt * percent / 100);
+    }
+
+    /// Moves the boss into every phase its health has dropped far enough for.
+    fn advance_boss(&mut self) {
+        let Some(boss) = self.boss.as_mut() else { return; };
+        let Some(army) = self.armies.iter_mut().find(|a| a.id == boss.id) else { return; };
+
+        let mut reinforcements = 0;
+        let mut enraged = None;
+        while let Some(phase) = boss.phases.get(boss.phase) {
+            if army.health * 100 >= phase.below_percent * army.max_health {
+                break;
+            }
+            match phase.ability {
+                BossAbility::Cleave => boss.cleaves = true,
+                BossAbility::Enrage(amount) => {
+                    army.max_countdown = army.max_countdown.saturating_sub(amount).max(1);
+                    army.countdown = army.countdown.min(army.max_countdown);
+                    enraged = Some(army.countdown);
+                }
+                BossAbility::Reinforce(amount) => reinforcements += amount,
+            }
+            boss.phase += 1;
+        }
+
+        let boss = boss.id;
+        if let Some(countdown) = enraged {
+            self.emit(CombatEvent::CountdownChanged { source: boss, target: boss, countdown });
+        }
+        for _ in 0..reinforcements {
+            if let Some(id) = self.call_in_any() {
+                self.emit(CombatEvent::UnitSummoned { summoner: boss, unit: id });
+            }
+        }
+    }
+}
+
+/// Whether a heal would do a minion any good, so that heals aimed at a single minion aren't
+/// wasted.
+fn can_heal(minion: &BattleMinion) -> bool {
+    minion.health < minion.kind.max_health && !minion.statuses.has(StatusKind::Poison)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::army::ArmyScaling;
+    use crate::class::{ClassList, Classes};
+    use crate::from_ron;
+    use crate::synergy::Synergies;
+
+    fn classes() -> Classes {
+        Classes::new(&from_ron::<ClassList>(include_str!("../assets/classes.ron")).unwrap()).unwrap()
+    }
+
+    fn class(name: &str) -> MinionType {
+        let classes = classes();
+        let mut class = classes.get(name).unwrap().clone();
+        class.triggered.clear();
+        class
+    }
+
+    /// Steps the battle until it is over, or gives up after `max_ticks`.
+    fn fight(battle: &mut Battle, max_ticks: usize) -> Outcome {
+        for _ in 0..max_ticks {
+            let outcome = battle.step();
+            if outcome != Outcome::Ongoing {
+                return outcome;
+            }
+        }
+        Outcome::Ongoing
+    }
+
+    #[test]
+    fn beats_a_wave_it_outdamages() {
+        let mut arcanist = class("Arcanist");
+        arcanist.ability = Damage;
+        arcanist.amount = 10;
+        let mut battle = Battle::new(0);
+        battle.add_minion(&arcanist);
+        battle.add_army("Soldier", "soldier.png", Behaviour::Soldier, 5, 1, 5);
+
+        assert_eq!(fight(&mut battle, 50), Outcome::Victory);
+        assert!(battle.armies.is_empty());
+        assert_eq!(battle.minions.len(), 1);
+    }
+
+    #[test]
+    fn loses_to_a_wave_it_cannot_hurt() {
+        let mut cleric = class("Cleric");
+        cleric.ability = Heal;
+        let mut battle = Battle::new(0);
+        battle.add_minion(&cleric);
+        battle.add_army("Soldier", "soldier.png", Behaviour::Soldier, 5, 100, 1);
+
+        assert_eq!(fight(&mut battle, 50), Outcome::Defeat);
+        assert!(battle.minions.is_empty());
+    }
+
+    #[test]
+    fn stuns_for_as_many_ticks_as_it_says() {
+        for ticks in 1..=3 {
+            let mut arcanist = class("Arcanist");
+            arcanist.ability = Afflict;
+            arcanist.status = Some(Status { kind: StatusKind::Stun, amount: 0, ticks });
+            arcanist.start_countdown = 1;
+            arcanist.max_countdown = 100;
+            let mut battle = Battle::new(0);
+            battle.add_minion(&arcanist);
+            battle.add_army("Soldier", "soldier.png", Behaviour::Soldier, 5, 1, 50);
+
+            // Stunned in the first tick, after the army counted down for it.
+            for _ in 0..10 {
+                battle.step();
+            }
+            assert_eq!(battle.armies[0].countdown, 50 - (10 - ticks));
+        }
+    }
+
+    #[test]
+    fn grows_for_good_at_the_end_of_the_fight() {
+        let mut priest = classes().get("Priest").unwrap().clone();
+        priest.ability = Damage;
+        priest.amount = 10;
+        let mut battle = Battle::new(0);
+        let id = battle.add_minion(&priest);
+        battle.add_army("Soldier", "soldier.png", Behaviour::Soldier, 5, 1, 5);
+
+        assert_eq!(fight(&mut battle, 50), Outcome::Victory);
+        assert_eq!(battle.grown, vec![(id, 1)]);
+        assert_eq!(battle.tallies[0].healing_done, 0);
+    }
+
+    #[test]
+    fn the_same_seed_fights_the_same_way() {
+        let classes = classes();
+        let scaling: ArmyScaling = from_ron(include_str!("../assets/armies.ron")).unwrap();
+        let synergies: Synergies = from_ron(include_str!("../assets/synergies.ron")).unwrap();
+        let party: Vec<_> = ["Warrior", "Cleric", "Arcanist", "Necromancer", "Priest"].iter()
+            .map(|name| (classes.get(name).unwrap().clone(), 4))
+            .collect();
+
+        // The fifth round has a boss, so that its phases are covered as well.
+        let run = |seed| {
+            let mut battle = Battle::start(5, &party, &scaling, &synergies, &[], seed);
+            let outcome = fight(&mut battle, 500);
+            (outcome, battle.ticks, battle.take_events())
+        };
+        let (outcome, ticks, events) = run(42);
+        assert_ne!(outcome, Outcome::Ongoing);
+        assert_eq!(run(42), (outcome, ticks, events));
+    }
+}
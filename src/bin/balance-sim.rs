@@ -0,0 +1,236 @@
+//! Simulates fights with the game's combat rules to see how the classes hold up.
+//!
+//! For every power level it drafts parties the way the game offers cards (or uses the one given
+//! with `--party`), fights each of them many times and prints win rates, fight lengths and how much
+//! each class in the class list contributes. Party members are given a bounty of `power - 1`, as
+//! if they had been around since the first round.
+//!
+//! It builds without Bevy: `cargo run --no-default-features --bin balance-sim`.
+
+use std::process::exit;
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+
+use you_behind_it_must_leave::battle::{Battle, Outcome};
+use you_behind_it_must_leave::army::ArmyScaling;
+use you_behind_it_must_leave::class::{ClassList, Classes};
+use you_behind_it_must_leave::synergy::Synergies;
+use you_behind_it_must_leave::from_ron;
+
+const USAGE: &str = "usage: balance-sim [--seed <u64>] [--max-power <n>] [--parties <n>] [--fights <n>] [--party-size <n>] [--party <Class,Class,..>] [--max-ticks <n>] [--classes <file>] [--armies <file>] [--synergies <file>]";
+
+struct Options {
+    seed: u64,
+    max_power: usize,
+    /// Random parties drafted per power level, ignored if `party` is given.
+    parties: usize,
+    /// Fights per party and power level.
+    fights: usize,
+    party_size: usize,
+    party: Option<Vec<String>>,
+    /// Fights that last longer than this are counted as stalemates.
+    max_ticks: usize,
+    classes: String,
+    armies: String,
+    synergies: String,
+}
+
+#[derive(Default)]
+struct Results {
+    fights: usize,
+    wins: usize,
+    stalemates: usize,
+    /// Summed over fights that were won or lost.
+    ticks: usize,
+}
+
+#[derive(Default, Clone)]
+struct ClassResults {
+    /// Fights this class took part in, counting each copy in the party.
+    fights: usize,
+    wins: usize,
+    damage: usize,
+    healing: usize,
+}
+
+fn main() {
+    let options = match parse_options(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{err}");
+            eprintln!("{USAGE}");
+            exit(2);
+        }
+    };
+
+    let classes = std::fs::read_to_string(&options.classes)
+        .map_err(|err| err.to_string())
+        .and_then(|source| from_ron::<ClassList>(&source))
+        .and_then(|list| Classes::new(&list))
+        .unwrap_or_else(|err| {
+            eprintln!("could not load {}: {err}", options.classes);
+            exit(1);
+        });
+    let scaling = std::fs::read_to_string(&options.armies)
+        .map_err(|err| err.to_string())
+        .and_then(|source| from_ron::<ArmyScaling>(&source))
+        .and_then(|scaling| scaling.check().map(|()| scaling))
+        .unwrap_or_else(|err| {
+            eprintln!("could not load {}: {err}", options.armies);
+            exit(1);
+        });
+    let synergies = std::fs::read_to_string(&options.synergies)
+        .map_err(|err| err.to_string())
+        .and_then(|source| from_ron::<Synergies>(&source))
+        .unwrap_or_else(|err| {
+            eprintln!("could not load {}: {err}", options.synergies);
+            exit(1);
+        });
+    let party = options.party.as_ref().map(|party| {
+        party.iter().map(|name| match classes.get(name) {
+            Some(class) => class.clone(),
+            None => {
+                eprintln!("unknown class {name}");
+                exit(2);
+            }
+        }).collect::<Vec<_>>()
+    });
+
+    let mut rng = ChaCha12Rng::seed_from_u64(options.seed);
+    let mut class_results = vec![ClassResults::default(); classes.all().len()];
+
+    println!("seed: {}", options.seed);
+    println!();
+    println!("{:>5} {:>8} {:>7} {:>7} {:>7} {:>10}", "power", "fights", "win%", "loss%", "stale%", "avg ticks");
+
+    for power in 1..=options.max_power {
+        let mut results = Results::default();
+        let parties = if party.is_some() { 1 } else { options.parties };
+
+        for _ in 0..parties {
+            let party: Vec<_> = match &party {
+                Some(party) => party.iter().map(|class| (class.clone(), power - 1)).collect(),
+                None => (0..options.party_size)
+                    .map(|_| (classes.generate(power.min(16), &mut rng), power - 1))
+                    .collect(),
+            };
+
+            for _ in 0..options.fights {
+                let mut battle = Battle::start(power, &party, &scaling, &synergies, &[], rng.gen());
+                let outcome = fight(&mut battle, options.max_ticks);
+
+                results.fights += 1;
+                match outcome {
+                    Outcome::Victory => results.wins += 1,
+                    Outcome::Defeat => {}
+                    Outcome::Ongoing => results.stalemates += 1,
+                }
+                if outcome != Outcome::Ongoing {
+                    results.ticks += battle.ticks;
+                }
+
+                for tally in battle.tallies.iter() {
+                    // Summons are credited to whoever summoned them.
+                    let credited = &battle.tally(battle.credited_to(tally.id)).unwrap().class;
+                    let Some(index) = classes.all().iter().position(|class| &class.name == credited) else { continue; };
+                    let class = &mut class_results[index];
+                    class.damage += tally.damage_dealt;
+                    class.healing += tally.healing_done;
+                    if tally.summoned_by.is_none() {
+                        class.fights += 1;
+                        if outcome == Outcome::Victory {
+                            class.wins += 1;
+                        }
+                    }
+                }
+            }
+        }
+
+        let decided = results.fights - results.stalemates;
+        println!(
+            "{:>5} {:>8} {:>7.1} {:>7.1} {:>7.1} {:>10.1}",
+            power,
+            results.fights,
+            percent(results.wins, results.fights),
+            percent(decided - results.wins, results.fights),
+            percent(results.stalemates, results.fights),
+            results.ticks as f64 / decided.max(1) as f64,
+        );
+    }
+
+    println!();
+    println!("{:<12} {:>8} {:>7} {:>10} {:>10}", "class", "fights", "win%", "dmg/fight", "heal/fight");
+    for (class, results) in classes.all().iter().zip(class_results.iter()) {
+        let fights = results.fights.max(1) as f64;
+        println!(
+            "{:<12} {:>8} {:>7.1} {:>10.1} {:>10.1}",
+            class.name,
+            results.fights,
+            percent(results.wins, results.fights),
+            results.damage as f64 / fights,
+            results.healing as f64 / fights,
+        );
+    }
+}
+
+/// Steps the battle until it is decided, or returns `Outcome::Ongoing` if it takes too long.
+fn fight(battle: &mut Battle, max_ticks: usize) -> Outcome {
+    while battle.ticks < max_ticks {
+        match battle.step() {
+            Outcome::Ongoing => {}
+            outcome => return outcome,
+        }
+    }
+    Outcome::Ongoing
+}
+
+fn percent(part: usize, whole: usize) -> f64 {
+    100.0 * part as f64 / whole.max(1) as f64
+}
+
+fn parse_options(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options {
+        seed: 0,
+        max_power: 20,
+        parties: 100,
+        fights: 100,
+        party_size: 3,
+        party: None,
+        max_ticks: 1000,
+        classes: "assets/classes.ron".into(),
+        armies: "assets/armies.ron".into(),
+        synergies: "assets/synergies.ron".into(),
+    };
+
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().ok_or(format!("missing value for {name}"));
+        match arg.as_str() {
+            "--seed" => options.seed = parse_value("--seed", value("--seed")?)?,
+            "--max-power" => options.max_power = parse_value("--max-power", value("--max-power")?)?,
+            "--parties" => options.parties = parse_value("--parties", value("--parties")?)?,
+            "--fights" => options.fights = parse_value("--fights", value("--fights")?)?,
+            "--party-size" => options.party_size = parse_value("--party-size", value("--party-size")?)?,
+            "--max-ticks" => options.max_ticks = parse_value("--max-ticks", value("--max-ticks")?)?,
+            "--party" => options.party = Some(value("--party")?.split(',').map(|name| name.trim().to_string()).collect()),
+            "--classes" => options.classes = value("--classes")?,
+            "--armies" => options.armies = value("--armies")?,
+            "--synergies" => options.synergies = value("--synergies")?,
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                exit(0);
+            }
+            _ => return Err(format!("unknown argument {arg}")),
+        }
+    }
+
+    if options.max_power == 0 || options.party_size == 0 {
+        return Err("--max-power and --party-size must be at least 1".into());
+    }
+
+    Ok(options)
+}
+
+fn parse_value<T: std::str::FromStr>(name: &str, value: String) -> Result<T, String> {
+    value.parse().map_err(|_| format!("invalid value for {name}: {value}"))
+}
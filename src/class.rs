@@ -0,0 +1,398 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::army::Scale;
+use crate::item::Item;
+use crate::status::Status;
+use crate::targeting::Targeting;
+
+/// The number of draft tiers. Which tier a draft card comes from depends on the draft power.
+pub const TIERS: usize = 4;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MinionType {
+    pub name: String,
+    pub tier: usize,
+    pub sprite: String,
+    pub ability: Ability,
+    /// How strong the ability is: the damage it deals, the health it heals or how much it changes
+    /// a countdown by.
+    pub amount: usize,
+    /// The minion the ability summons, for abilities that summon.
+    pub summon: Option<Box<MinionType>>,
+    /// Abilities it uses when something happens in the fight, on top of the one on its countdown.
+    #[serde(default)]
+    pub triggered: Vec<TriggeredAbility>,
+    /// The status its abilities give to the armies they hit or target, or to every minion for
+    /// `AoeGrant`.
+    #[serde(default)]
+    pub status: Option<Status>,
+    /// Whether its abilities can hit or target armies in the back line.
+    #[serde(default)]
+    pub reach: bool,
+    /// Whether armies have to attack it, and other taunting minions, before anyone else.
+    #[serde(default)]
+    pub taunt: bool,
+    /// Which army or minion its abilities aimed at a single unit go to.
+    #[serde(default)]
+    pub targeting: Targeting,
+    /// The class it can become once its bounty is high enough.
+    #[serde(default)]
+    pub upgrade: Option<Upgrade>,
+    /// How many times copies of it have been merged into it.
+    #[serde(default)]
+    pub stars: usize,
+    /// The item it carries into fights.
+    #[serde(default)]
+    pub item: Option<Item>,
+    pub penalties: Vec<Penalty>,
+    pub types: Vec<Type>,
+    pub max_countdown: usize,
+    pub start_countdown: usize,
+    pub max_health: usize,
+}
+
+/// A class a minion can be upgraded into, keeping its place in the party and its bounty.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Upgrade {
+    pub into: String,
+    /// The bounty the minion needs before it can upgrade.
+    pub bounty: usize,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum Penalty {
+    IncreasedHealth,
+    IncreasedDamage,
+    IncreasedAmount,
+    ReducedCountdown,
+}
+
+/// What a minion does when its countdown runs out. Classes pick one and set how strong it is with
+/// their `amount`, so a new class can reuse any of these without touching the code.
+///
+/// Abilities aimed at a single army can only go to one in the front line, unless the class has
+/// `reach`, and the class's `targeting` picks which. Any minion can be picked the same way.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Ability {
+    /// Deals `amount` damage to an army.
+    Damage,
+    /// Deals `amount` damage to every army.
+    AoeDamage,
+    /// Heals a minion by `amount`, up to its max health.
+    Heal,
+    /// Heals every minion by `amount`, up to their max health.
+    AoeHeal,
+    /// Heals every minion by `amount`, past their max health.
+    AoeOverheal,
+    /// Takes `amount` off a minion's countdown.
+    Reduce,
+    /// Takes `amount` off every minion's countdown.
+    AoeReduce,
+    /// Adds `amount` to an army's countdown.
+    Delay,
+    /// Adds the class's `summon` to the party.
+    Summon,
+    /// Kills an army outright.
+    Execute,
+    /// Raises the max health and health of the minion that triggered it by `amount`, or its own if
+    /// there is no such minion.
+    Empower,
+    /// Raises the max health of the minion that triggered it by `amount` for the rest of the run,
+    /// or its own if there is no such minion.
+    Grow,
+    /// Gives an army the class's status.
+    Afflict,
+    /// Gives every minion the class's status.
+    AoeGrant,
+}
+
+/// An ability used when something happens in the fight instead of when a countdown runs out.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct TriggeredAbility {
+    pub trigger: Trigger,
+    pub ability: Ability,
+    #[serde(default)]
+    pub amount: usize,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Trigger {
+    /// Before the first tick.
+    FightStart,
+    /// Once the wave is beaten, before the fight is won. Health and countdowns start over in the
+    /// next fight, so only `Grow` is allowed here.
+    FightEnd,
+    /// Another minion in the party died. That minion triggered it.
+    AllyDied,
+    /// It killed an army.
+    Kill,
+    /// It summoned a minion. The summoned minion triggered it.
+    Summon,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Type {
+    Martial,
+    Caster,
+    Divine,
+    Envoy,
+}
+
+/// A class as written in a class list, before the class it summons has been looked up.
+#[derive(Deserialize, Clone)]
+pub struct ClassDef {
+    pub name: String,
+    pub tier: usize,
+    pub sprite: String,
+    pub ability: Ability,
+    #[serde(default)]
+    pub amount: usize,
+    #[serde(default)]
+    pub summon: Option<String>,
+    #[serde(default)]
+    pub triggered: Vec<TriggeredAbility>,
+    #[serde(default)]
+    pub status: Option<Status>,
+    #[serde(default)]
+    pub reach: bool,
+    #[serde(default)]
+    pub taunt: bool,
+    #[serde(default)]
+    pub targeting: Targeting,
+    #[serde(default)]
+    pub upgrade: Option<Upgrade>,
+    pub penalties: Vec<Penalty>,
+    pub types: Vec<Type>,
+    pub max_countdown: usize,
+    pub start_countdown: usize,
+    pub max_health: usize,
+}
+
+/// How much stronger a minion gets for each star, from merging three copies of it with the same
+/// number of stars. Its health and the amounts of its abilities are scaled and its countdown gets
+/// shorter by `countdown`, but stays at least one tick.
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub struct StarScaling {
+    pub max_stars: usize,
+    pub health: Scale,
+    pub amount: Scale,
+    pub countdown: usize,
+}
+
+/// The `classes.ron` asset file listing the classes that can be drafted.
+#[derive(Deserialize)]
+pub struct ClassList {
+    pub classes: Vec<ClassDef>,
+    pub stars: StarScaling,
+}
+
+/// The classes that can be drafted, checked and with summons looked up.
+#[derive(Clone)]
+pub struct Classes {
+    classes: Vec<MinionType>,
+    stars: StarScaling,
+}
+
+impl Classes {
+    pub fn new(list: &ClassList) -> Result<Classes, String> {
+        let classes = list.classes.iter()
+            .map(|def| resolve(&list.classes, def, 0))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for tier in 0..TIERS {
+            if !classes.iter().any(|class| class.tier == tier) {
+                return Err(format!("there are no classes in tier {tier}"));
+            }
+        }
+        if let Some(class) = classes.iter().find(|class| class.tier >= TIERS) {
+            return Err(format!("{} is in tier {}, but the highest tier is {}", class.name, class.tier, TIERS - 1));
+        }
+        for class in classes.iter() {
+            let fight_end = class.triggered.iter().find(|t| t.trigger == Trigger::FightEnd && t.ability != Ability::Grow);
+            if let Some(triggered) = fight_end {
+                return Err(format!("{} uses {:?} at the end of the fight, when it does nothing", class.name, triggered.ability));
+            }
+        }
+        for class in classes.iter() {
+            let Some(upgrade) = &class.upgrade else { continue; };
+            let into = classes.iter()
+                .find(|c| c.name == upgrade.into)
+                .ok_or(format!("{} upgrades into {}, which is not a class", class.name, upgrade.into))?;
+            // Upgrades only going up a tier also keeps them from going round in circles.
+            if into.tier <= class.tier {
+                return Err(format!("{} upgrades into {}, which is not in a higher tier", class.name, into.name));
+            }
+        }
+
+        Ok(Classes { classes, stars: list.stars })
+    }
+
+    pub fn all(&self) -> &[MinionType] {
+        &self.classes
+    }
+
+    pub fn get(&self, name: &str) -> Option<&MinionType> {
+        self.classes.iter().find(|class| class.name == name)
+    }
+
+    /// What a minion with `bounty` can be upgraded into, if anything. It keeps its stars and its
+    /// item.
+    pub fn upgrade(&self, minion: &MinionType, bounty: usize) -> Option<MinionType> {
+        let upgrade = minion.upgrade.as_ref().filter(|upgrade| bounty >= upgrade.bounty)?;
+        let mut upgraded = self.get(&upgrade.into)?.clone();
+        for _ in 0..minion.stars {
+            upgraded = self.star(&upgraded);
+        }
+        upgraded.item = minion.item.clone();
+        Some(upgraded)
+    }
+
+    /// What three copies of `minion` merge into, or `None` if it already has the most stars.
+    pub fn merge(&self, minion: &MinionType) -> Option<MinionType> {
+        (minion.stars < self.stars.max_stars).then(|| self.star(minion))
+    }
+
+    fn star(&self, minion: &MinionType) -> MinionType {
+        let mut starred = minion.clone();
+        starred.stars += 1;
+        starred.max_health = self.stars.health.apply(minion.max_health).max(minion.max_health);
+        starred.amount = self.stars.amount.apply(minion.amount).max(minion.amount);
+        for triggered in starred.triggered.iter_mut() {
+            triggered.amount = self.stars.amount.apply(triggered.amount).max(triggered.amount);
+        }
+        starred.max_countdown = minion.max_countdown.saturating_sub(self.stars.countdown).max(1);
+        starred
+    }
+
+    /// Picks the class for a draft card. The higher the power, the more likely it is to come from
+    /// a higher tier.
+    pub fn generate(&self, power: usize, rng: &mut impl Rng) -> MinionType {
+        let tier = match rng.gen_range(0..power) {
+            0..=4 => 0,
+            5..=8 => 1,
+            9..=12 => 2,
+            _ => 3,
+        };
+        let options: Vec<_> = self.classes.iter().filter(|class| class.tier == tier).collect();
+        options[rng.gen_range(0..options.len())].clone()
+    }
+}
+
+fn resolve(defs: &[ClassDef], def: &ClassDef, depth: usize) -> Result<MinionType, String> {
+    if depth > defs.len() {
+        return Err(format!("{} ends up summoning itself", def.name));
+    }
+
+    let summon = match &def.summon {
+        Some(name) => {
+            let summoned = defs.iter()
+                .find(|d| &d.name == name)
+                .ok_or(format!("{} summons {name}, which is not a class", def.name))?;
+            Some(Box::new(resolve(defs, summoned, depth + 1)?))
+        }
+        None if def.ability == Ability::Summon || def.triggered.iter().any(|t| t.ability == Ability::Summon) => {
+            return Err(format!("{} has the {:?} ability but does not summon anything", def.name, Ability::Summon));
+        }
+        None => None,
+    };
+
+    Ok(MinionType {
+        name: def.name.clone(),
+        tier: def.tier,
+        sprite: def.sprite.clone(),
+        ability: def.ability,
+        amount: def.amount,
+        summon,
+        triggered: def.triggered.clone(),
+        status: def.status,
+        reach: def.reach,
+        taunt: def.taunt,
+        targeting: def.targeting,
+        upgrade: def.upgrade.clone(),
+        stars: 0,
+        item: None,
+        penalties: def.penalties.clone(),
+        types: def.types.clone(),
+        max_countdown: def.max_countdown,
+        start_countdown: def.start_countdown,
+        max_health: def.max_health,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha12Rng;
+
+    use super::*;
+    use crate::from_ron;
+
+    fn shipped() -> ClassList {
+        from_ron(include_str!("../assets/classes.ron")).unwrap()
+    }
+
+    fn class<'a>(list: &'a mut ClassList, name: &str) -> &'a mut ClassDef {
+        list.classes.iter_mut().find(|def| def.name == name).unwrap()
+    }
+
+    fn error(list: &ClassList) -> String {
+        Classes::new(list).err().expect("the class list should be rejected")
+    }
+
+    #[test]
+    fn the_same_seed_drafts_the_same_cards() {
+        let classes = Classes::new(&shipped()).unwrap();
+        let draft = |seed| {
+            let mut rng = ChaCha12Rng::seed_from_u64(seed);
+            (1..=16).map(|power| classes.generate(power, &mut rng).name).collect::<Vec<_>>()
+        };
+        assert_eq!(draft(7), draft(7));
+    }
+
+    #[test]
+    fn rejects_missing_and_unknown_tiers() {
+        let mut list = shipped();
+        for def in list.classes.iter_mut().filter(|def| def.tier == 3) {
+            def.tier = 2;
+        }
+        assert_eq!(error(&list), "there are no classes in tier 3");
+
+        let mut list = shipped();
+        class(&mut list, "Archmage").tier = TIERS;
+        assert_eq!(error(&list), "Archmage is in tier 4, but the highest tier is 3");
+    }
+
+    #[test]
+    fn rejects_bad_summons() {
+        let mut list = shipped();
+        class(&mut list, "Necromancer").summon = Some("Nobody".into());
+        assert_eq!(error(&list), "Necromancer summons Nobody, which is not a class");
+
+        let mut list = shipped();
+        class(&mut list, "Necromancer").summon = Some("Necromancer".into());
+        assert_eq!(error(&list), "Necromancer ends up summoning itself");
+
+        let mut list = shipped();
+        class(&mut list, "Necromancer").summon = None;
+        assert_eq!(error(&list), "Necromancer has the Summon ability but does not summon anything");
+    }
+
+    #[test]
+    fn rejects_bad_upgrades() {
+        let mut list = shipped();
+        class(&mut list, "Priest").upgrade.as_mut().unwrap().into = "Nobody".into();
+        assert_eq!(error(&list), "Priest upgrades into Nobody, which is not a class");
+
+        let mut list = shipped();
+        class(&mut list, "Priest").upgrade.as_mut().unwrap().into = "Cleric".into();
+        assert_eq!(error(&list), "Priest upgrades into Cleric, which is not in a higher tier");
+    }
+
+    #[test]
+    fn rejects_abilities_that_do_nothing_at_the_end_of_the_fight() {
+        let mut list = shipped();
+        class(&mut list, "Priest").triggered[0].ability = Ability::AoeHeal;
+        assert_eq!(error(&list), "Priest uses AoeHeal at the end of the fight, when it does nothing");
+    }
+}
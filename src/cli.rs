@@ -0,0 +1,79 @@
+use std::process::exit;
+
+use chrono::Utc;
+
+const USAGE: &str = "usage: you-behind-it-must-leave [--seed <u64>] [--power <n>] [--speed <ticks per second>] [--record <file>] [--replay <file>] [--save <file>] [--combat-log]";
+
+/// Settings for a run, taken from the command line.
+///
+/// Two runs with the same seed where the player makes the same choices play out identically.
+pub struct Options {
+    pub seed: u64,
+    pub power: usize,
+    pub speed: f32,
+    /// Where the decisions of this run are recorded.
+    pub record: String,
+    /// A recorded run to play back instead of taking input. Overrides the seed and power.
+    pub replay: Option<String>,
+    /// Where the run is saved, to be continued the next time the game starts.
+    pub save: String,
+    /// Print everything that happens in fights.
+    pub combat_log: bool,
+}
+
+impl Options {
+    /// Parses the process arguments, printing usage and exiting if they don't make sense.
+    pub fn parse() -> Options {
+        match Options::parse_from(std::env::args().skip(1)) {
+            Ok(options) => options,
+            Err(err) => {
+                eprintln!("{err}");
+                eprintln!("{USAGE}");
+                exit(2);
+            }
+        }
+    }
+
+    fn parse_from(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+        let mut options = Options {
+            seed: Utc::now().timestamp_millis() as u64,
+            power: 1,
+            speed: 1.0,
+            record: "last-run.replay".into(),
+            replay: None,
+            save: "last-run.save".into(),
+            combat_log: false,
+        };
+
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| args.next().ok_or(format!("missing value for {name}"));
+            match arg.as_str() {
+                "--seed" => options.seed = parse_value("--seed", value("--seed")?)?,
+                "--power" => options.power = parse_value("--power", value("--power")?)?,
+                "--speed" => options.speed = parse_value("--speed", value("--speed")?)?,
+                "--record" => options.record = value("--record")?,
+                "--replay" => options.replay = Some(value("--replay")?),
+                "--save" => options.save = value("--save")?,
+                "--combat-log" => options.combat_log = true,
+                "-h" | "--help" => {
+                    println!("{USAGE}");
+                    exit(0);
+                }
+                _ => return Err(format!("unknown argument {arg}")),
+            }
+        }
+
+        if options.power == 0 {
+            return Err("--power must be at least 1".into());
+        }
+        if !options.speed.is_finite() || options.speed <= 0.0 {
+            return Err("--speed must be positive".into());
+        }
+
+        Ok(options)
+    }
+}
+
+fn parse_value<T: std::str::FromStr>(name: &str, value: String) -> Result<T, String> {
+    value.parse().map_err(|_| format!("invalid value for {name}: {value}"))
+}
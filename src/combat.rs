@@ -0,0 +1,112 @@
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+
+use you_behind_it_must_leave::battle::{CombatEvent, UnitId};
+
+// The units that cause things are `None` if they left the fight in an earlier tick, like a
+// minion whose poison outlives it.
+
+/// `target` lost `amount` health to `source`.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct DamageDealt {
+    pub source: Option<Entity>,
+    pub target: Entity,
+    pub amount: usize,
+}
+
+/// `target` gained `amount` health from `source`, which can be itself.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct Healed {
+    pub source: Option<Entity>,
+    pub target: Entity,
+    pub amount: usize,
+}
+
+/// `unit` was removed from the fight. Its entity is despawned once the event has been read.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct UnitDied {
+    pub unit: Entity,
+    pub killer: Option<Entity>,
+}
+
+/// `unit` joined the fight, summoned or called in by `summoner`. The summoner may have died in
+/// the same tick.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct UnitSummoned {
+    pub summoner: Option<Entity>,
+    pub unit: Entity,
+}
+
+/// `source` changed the countdown of `target` to `countdown`.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct CountdownChanged {
+    pub source: Option<Entity>,
+    pub target: Entity,
+    pub countdown: usize,
+}
+
+/// Sends what happened in the battle as events about the entities the units are spawned as.
+#[derive(SystemParam)]
+pub struct CombatEvents<'w> {
+    damage_dealt: EventWriter<'w, DamageDealt>,
+    healed: EventWriter<'w, Healed>,
+    unit_died: EventWriter<'w, UnitDied>,
+    unit_summoned: EventWriter<'w, UnitSummoned>,
+    countdown_changed: EventWriter<'w, CountdownChanged>,
+}
+
+impl CombatEvents<'_> {
+    /// Sends an event for each of `events`, leaving out the ones about units `entity` can't find.
+    /// The units that caused them don't have to be found.
+    pub fn send(&mut self, events: Vec<CombatEvent>, entity: impl Fn(UnitId) -> Option<Entity>) {
+        for event in events {
+            match event {
+                CombatEvent::DamageDealt { source, target, amount } => {
+                    let (source, Some(target)) = (entity(source), entity(target)) else { continue; };
+                    self.damage_dealt.send(DamageDealt { source, target, amount });
+                }
+                CombatEvent::Healed { source, target, amount } => {
+                    let (source, Some(target)) = (entity(source), entity(target)) else { continue; };
+                    self.healed.send(Healed { source, target, amount });
+                }
+                CombatEvent::UnitDied { unit, killer } => {
+                    let (Some(unit), killer) = (entity(unit), entity(killer)) else { continue; };
+                    self.unit_died.send(UnitDied { unit, killer });
+                }
+                CombatEvent::UnitSummoned { summoner, unit } => {
+                    let (summoner, Some(unit)) = (entity(summoner), entity(unit)) else { continue; };
+                    self.unit_summoned.send(UnitSummoned { summoner, unit });
+                }
+                CombatEvent::CountdownChanged { source, target, countdown } => {
+                    let (source, Some(target)) = (entity(source), entity(target)) else { continue; };
+                    self.countdown_changed.send(CountdownChanged { source, target, countdown });
+                }
+            }
+        }
+    }
+}
+
+/// Prints every combat event, for following a fight blow by blow.
+pub fn log_combat(
+    mut damage_dealt: EventReader<DamageDealt>,
+    mut healed: EventReader<Healed>,
+    mut unit_died: EventReader<UnitDied>,
+    mut unit_summoned: EventReader<UnitSummoned>,
+    mut countdown_changed: EventReader<CountdownChanged>,
+) {
+    for event in damage_dealt.read() {
+        println!("{:?} dealt {} damage to {:?}", event.source, event.amount, event.target);
+    }
+    for event in healed.read() {
+        println!("{:?} healed {:?} by {}", event.source, event.target, event.amount);
+    }
+    for event in countdown_changed.read() {
+        println!("{:?} set the countdown of {:?} to {}", event.source, event.target, event.countdown);
+    }
+    for event in unit_summoned.read() {
+        println!("{:?} summoned {:?}", event.summoner, event.unit);
+    }
+    for event in unit_died.read() {
+        println!("{:?} was killed by {:?}", event.unit, event.killer);
+    }
+}
@@ -0,0 +1,48 @@
+use serde::{Deserialize, Serialize};
+
+use crate::class::MinionType;
+use crate::synergy::Bonus;
+
+/// Something a single minion can carry into fights.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Item {
+    pub name: String,
+    pub icon: String,
+    pub effect: ItemEffect,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ItemEffect {
+    /// More max health.
+    Health(usize),
+    /// A shorter countdown, down to a single tick.
+    Countdown(usize),
+    /// Heals the minion by this percentage of the damage its abilities deal.
+    Lifesteal(usize),
+    /// Its abilities aimed at a single unit are used on one more, picked the same way.
+    ExtraTarget,
+}
+
+impl ItemEffect {
+    /// Changes the stats of a minion that carries the item as it joins a fight. Effects that work
+    /// during the fight are left to the battle.
+    pub fn apply(self, kind: &mut MinionType) {
+        match self {
+            ItemEffect::Health(amount) => kind.max_health += amount,
+            ItemEffect::Countdown(amount) => {
+                kind.max_countdown = kind.max_countdown.saturating_sub(amount).max(1);
+                kind.start_countdown = kind.start_countdown.saturating_sub(amount);
+            }
+            ItemEffect::Lifesteal(_) | ItemEffect::ExtraTarget => {}
+        }
+    }
+}
+
+/// Something that makes the whole party better in every fight, the same way a synergy does.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Relic {
+    pub name: String,
+    pub icon: String,
+    pub bonus: Bonus,
+    pub amount: usize,
+}
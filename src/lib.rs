@@ -0,0 +1,20 @@
+//! Game rules and the replay format, which don't need Bevy at all, shared by the game and the
+//! `balance-sim` binary.
+
+pub mod army;
+pub mod battle;
+pub mod class;
+pub mod item;
+pub mod replay;
+pub mod shop;
+pub mod status;
+pub mod synergy;
+pub mod targeting;
+
+use serde::de::DeserializeOwned;
+
+/// Reads balance data written out as RON straight from its source, for when there is no
+/// `AssetServer` around. Checking that it makes sense is left to the type.
+pub fn from_ron<T: DeserializeOwned>(source: &str) -> Result<T, String> {
+    ron::de::from_str(source).map_err(|err| err.to_string())
+}
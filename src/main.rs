@@ -1,320 +1,510 @@
+#![allow(clippy::too_many_arguments, clippy::type_complexity)]
+
+use std::collections::{HashMap, VecDeque};
 use std::process::exit;
 
-use bevy::{prelude::*, input::{ButtonState, mouse::MouseButtonInput}};
+use bevy::{prelude::*, app::AppExit, input::{ButtonState, mouse::MouseButtonInput}};
 use bevy_inspector_egui::quick::WorldInspectorPlugin;
-use chrono::Utc;
-use rand::Rng;
-use rand::rngs::StdRng;
-
-use crate::Class::*;
-use crate::Penalty::*;
-use crate::Type::*;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+
+use you_behind_it_must_leave::battle::{Battle, BattleArmy, Outcome, UnitId, FRONT_LINE};
+use you_behind_it_must_leave::army::ArmyScaling;
+use you_behind_it_must_leave::class::{Classes, MinionType};
+use you_behind_it_must_leave::item::Relic;
+use you_behind_it_must_leave::replay::{Decision, Recorder, ReplayFile};
+use you_behind_it_must_leave::shop::{Purse, Shop, Ware};
+use you_behind_it_must_leave::status::{StatusKind, Statuses};
+use you_behind_it_must_leave::synergy::{Bonus, Synergies};
+
+use crate::balance::{ArmyFile, Balance, ClassFile, RonLoader, ShopFile, SynergyFile};
+use crate::cli::Options;
+use crate::combat::{CombatEvents, CountdownChanged, DamageDealt, Healed, UnitDied, UnitSummoned};
+use crate::save::{SaveFile, SavedPhase};
+use crate::stats::RunStats;
+
+mod balance;
+mod cli;
+mod combat;
+mod save;
+mod stats;
 
 fn main() {
     std::env::set_var("RUST_BACKTRACE", "1");
-    let cur = Utc::now().timestamp_millis();
-    let rng = rand::SeedableRng::seed_from_u64(cur as u64);
+    let mut options = Options::parse();
+
+    let mut app = App::new();
+
+    if let Some(path) = &options.replay {
+        let replay = ReplayFile::load(path).unwrap_or_else(|err| {
+            eprintln!("{err}");
+            exit(1);
+        });
+        options.seed = replay.seed;
+        options.power = replay.power;
+        app.insert_resource(Replay(replay.decisions.into()));
+    } else {
+        // Replays never touch the save, so that watching one doesn't lose the run in progress.
+        match SaveFile::load(&options.save) {
+            Ok(Some(saved)) => { app.insert_resource(saved); }
+            Ok(None) => {}
+            Err(err) => eprintln!("not continuing: {err}"),
+        }
+        app.insert_resource(SavePath(options.save.clone()));
+
+        match Recorder::create(&options.record, options.seed, options.power) {
+            Ok(recorder) => { app.insert_resource(Recording(recorder)); }
+            Err(err) => eprintln!("not recording to {}: {err}", options.record),
+        }
+    }
 
-    App::new()
+    if options.combat_log {
+        app.add_systems(Update, combat::log_combat.after(fight_tick));
+    }
+
+    println!("seed: {}", options.seed);
+    let rng = ChaCha12Rng::seed_from_u64(options.seed);
+
+    app
         .add_plugins(DefaultPlugins)
         .add_plugins(WorldInspectorPlugin::new())
+        .init_asset::<ClassFile>()
+        .init_asset::<ArmyFile>()
+        .init_asset::<SynergyFile>()
+        .init_asset::<ShopFile>()
+        .register_asset_loader(RonLoader::<ClassFile>::default())
+        .register_asset_loader(RonLoader::<ArmyFile>::default())
+        .register_asset_loader(RonLoader::<SynergyFile>::default())
+        .register_asset_loader(RonLoader::<ShopFile>::default())
+        .init_state::<GamePhase>()
         .add_systems(Startup, setup)
-        .add_systems(Update, (set_health_bars, fight_tick, fight_start_pre, fight_start, draft_start, draft_tick, discard_start, discard_tick))
-        .add_event::<FightStart>()
-        .add_event::<DraftStart>()
-        .add_event::<FightPreload>()
-        .add_event::<DiscardStart>()
-        .insert_resource(FightTimer(Timer::from_seconds(1.0, TimerMode::Repeating)))
+        .add_systems(Update, load_balance)
+        .add_systems(Update, finish_loading.run_if(in_state(GamePhase::Loading)))
+        .add_systems(OnEnter(GamePhase::Menu), menu_start)
+        .add_systems(Update, menu_tick.run_if(in_state(GamePhase::Menu)))
+        .add_systems(OnExit(GamePhase::Menu), menu_end)
+        .add_systems(OnEnter(GamePhase::Draft), draft_start)
+        .add_systems(Update, draft_tick.run_if(in_state(GamePhase::Draft)))
+        .add_systems(OnExit(GamePhase::Draft), draft_end)
+        .add_systems(OnEnter(GamePhase::Discard), discard_start)
+        .add_systems(Update, discard_tick.run_if(in_state(GamePhase::Discard)))
+        .add_systems(OnExit(GamePhase::Discard), discard_end)
+        .add_systems(OnEnter(GamePhase::Fight), fight_start)
+        .add_systems(Update, (fight_tick, despawn_dead).chain().run_if(in_state(GamePhase::Fight)))
+        .add_systems(OnExit(GamePhase::Fight), fight_end)
+        .add_systems(OnEnter(GamePhase::Shop), shop_start)
+        .add_systems(Update, shop_tick.run_if(in_state(GamePhase::Shop)))
+        .add_systems(OnExit(GamePhase::Shop), shop_end)
+        .add_systems(OnEnter(GamePhase::GameOver), game_over_start)
+        .add_systems(Update, game_over_tick.run_if(in_state(GamePhase::GameOver)))
+        .add_systems(OnExit(GamePhase::GameOver), game_over_end)
+        .add_systems(Update, (read_input, replay_tick).before(draft_tick).before(discard_tick).before(shop_tick))
+        // Saves before anything that changes the party this frame, so the save matches the phase.
+        .add_systems(Update, autosave.before(draft_tick).before(discard_tick).before(fight_tick).before(shop_tick))
+        .add_systems(Update, (
+            set_health_bars,
+            // Its icons are children of the units, so it runs after the systems that despawn them.
+            set_status_icons.after(discard_tick).after(despawn_dead),
+            arrange_units.run_if(in_state(GamePhase::Discard).or_else(in_state(GamePhase::Fight))),
+        ))
+        .add_event::<Decided>()
+        .add_event::<Autosave>()
+        .add_event::<DamageDealt>()
+        .add_event::<Healed>()
+        .add_event::<UnitDied>()
+        .add_event::<UnitSummoned>()
+        .add_event::<CountdownChanged>()
+        .insert_resource(FightTimer(Timer::from_seconds(1.0 / options.speed, TimerMode::Repeating)))
         .insert_resource(Random(rng))
         .init_resource::<Party>()
-        .insert_resource(DraftSettings { power: 1 })
+        .init_resource::<RunStats>()
+        .init_resource::<PartyPurse>()
+        .insert_resource(DraftSettings { power: options.power, start_power: options.power, bonus: 0, locked: None })
         .run();
 }
 
+/// Where the run is at. Each phase spawns what it shows when it is entered and cleans it up when
+/// it is left.
+#[derive(States, Default, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+enum GamePhase {
+    /// Waiting for the balance data.
+    #[default]
+    Loading,
+    /// Asking whether to continue the saved run.
+    Menu,
+    Draft,
+    Discard,
+    Fight,
+    /// Spending gold after a won fight.
+    Shop,
+    /// The party was wiped out, showing how the run went.
+    GameOver,
+}
+
 #[derive(Resource, Deref, DerefMut)]
-struct Random(StdRng);
+struct Random(ChaCha12Rng);
 
-#[derive(Event, Clone, Copy)]
-struct DraftStart;
+/// Where the run is saved. Missing while a replay is playing.
+#[derive(Resource)]
+struct SavePath(String);
 
+/// A decision the player made, or that a replay made for them.
 #[derive(Event)]
-struct FightStart;
+struct Decided(Decision);
+
+/// Writes the run's decisions to its replay file. Missing while a replay is playing.
+#[derive(Resource, Deref, DerefMut)]
+struct Recording(Recorder);
+
+/// Decisions from a replay file that are yet to be fed to the game.
+#[derive(Resource, Deref, DerefMut)]
+struct Replay(VecDeque<Decision>);
 
+/// Sent as a phase starts, with the random number generator as it was before the phase used it.
 #[derive(Event)]
-struct FightPreload;
+struct Autosave {
+    phase: SavedPhase,
+    rng: ChaCha12Rng,
+}
 
+/// The text asking whether to continue the saved run.
 #[derive(Component)]
-struct DraftCardIndex(usize);
+struct ContinuePrompt;
 
-#[derive(Component, Clone)]
-struct MinionType {
-    class: Class,
-    penalties: Vec<Penalty>,
-    types: Vec<Type>,
-    max_countdown: usize,
-    start_countdown: usize,
-    max_health: usize,
+/// The list of synergies shown during the discard and the fight.
+#[derive(Component)]
+struct SynergyPanel;
+
+/// The relics and the items no one carries, shown during the discard.
+#[derive(Component)]
+struct BagPanel;
+
+/// The gold and the offers shown in the shop.
+#[derive(Component)]
+struct ShopPanel;
+
+/// The run summary shown once the party is wiped out.
+#[derive(Component)]
+struct GameOverScreen;
+
+#[derive(Component, Clone, Copy)]
+enum GameOverButton {
+    NewRun,
+    Quit,
 }
 
-impl MinionType {
-    fn get_minion(&self) -> Minion {
-        Minion {
-            countdown: self.start_countdown,
-            health: self.max_health,
-        }
-    }
+#[derive(Component)]
+struct DraftCardIndex(usize);
+
+/// The padlock on the locked draft card.
+#[derive(Component)]
+struct LockIcon;
+
+/// The row of buttons under the draft cards.
+#[derive(Component)]
+struct DraftButtons;
+
+#[derive(Component, Clone, Copy)]
+enum DraftButton {
+    /// Locks or unlocks the draft card at this index, counted from the left.
+    Lock(usize),
+    Reroll,
+    Skip,
 }
 
+/// The class of a minion or draft card, with everything merges, upgrades and items did to it.
+#[derive(Component, Deref)]
+struct Class(MinionType);
+
 #[derive(Component)]
 struct Minion {
-    countdown: usize,
     health: usize,
-}
-
-#[derive(Clone)]
-enum Penalty {
-    IncreasedHealth,
-    IncreasedDamage,
-    IncreasedAmount,
-    ReducedCountdown,
-}
-
-#[derive(Clone)]
-enum Class {
-    Arcanist,
-    Cleric,
-    Warrior,
-    Scout,
-    Pyromancer,
-    Priest,
-    Rogue,
-    Tactician,
-    Necromancer,
-    Bulwark,
-    Oracle,
-    Sage,
-    Archmage,
-    Pope,
-    Invincible,
-    General,
-}
-
-impl Class {
-    fn get_type(&self) -> MinionType {
-        match self {
-            &Arcanist => { MinionType {
-                class: Arcanist,
-                penalties: vec![IncreasedDamage],
-                types: vec![Caster],
-                max_countdown: 1,
-                start_countdown: 3,
-                max_health: 3,
-            } }
-            &Cleric => { MinionType {
-                class: Cleric,
-                penalties: vec![IncreasedHealth],
-                types: vec![Divine],
-                max_countdown: 3,
-                start_countdown: 3,
-                max_health: 8,
-            } }
-            &Warrior => { MinionType {
-                class: Warrior,
-                penalties: vec![IncreasedAmount],
-                types: vec![Martial],
-                max_countdown: 2,
-                start_countdown: 1,
-                max_health: 10,
-            } }
-            &Scout => { MinionType {
-                class: Scout,
-                penalties: vec![ReducedCountdown],
-                types: vec![Envoy],
-                max_countdown: 1,
-                start_countdown: 1,
-                max_health: 5,
-            } }
-            &Pyromancer => { MinionType {
-                class: Pyromancer,
-                penalties: vec![IncreasedAmount],
-                types: vec![Caster],
-                max_countdown: 3,
-                start_countdown: 3,
-                max_health: 5,
-            } }
-            &Priest => { MinionType {
-                class: Priest,
-                penalties: vec![IncreasedHealth],
-                types: vec![Divine],
-                max_countdown: 1,
-                start_countdown: 3,
-                max_health: 6,
-            } }
-            &Rogue => { MinionType {
-                class: Rogue,
-                penalties: vec![IncreasedDamage],
-                types: vec![Martial],
-                max_countdown: 1,
-                start_countdown: 1,
-                max_health: 4,
-            } }
-            &Tactician => { MinionType {
-                class: Tactician,
-                penalties: vec![ReducedCountdown],
-                types: vec![Envoy],
-                max_countdown: 4,
-                start_countdown: 3,
-                max_health: 8,
-            } }
-            &Necromancer => { MinionType {
-                class: Necromancer,
-                penalties: vec![IncreasedHealth],
-                types: vec![Caster, Divine],
-                max_countdown: 5,
-                start_countdown: 5,
-                max_health: 6,
-            } }
-            &Oracle => { MinionType {
-                class: Oracle,
-                penalties: vec![IncreasedAmount],
-                types: vec![Divine, Envoy],
-                max_countdown: 2,
-                start_countdown: 3,
-                max_health: 7,
-            } }
-            &Bulwark => { MinionType {
-                class: Bulwark,
-                penalties: vec![ReducedCountdown],
-                types: vec![Martial, Envoy],
-                max_countdown: 2,
-                start_countdown: 3,
-                max_health: 12,
-            } }
-            &Sage => { MinionType {
-                class: Sage,
-                penalties: vec![IncreasedDamage],
-                types: vec![Caster, Martial],
-                max_countdown: 3,
-                start_countdown: 4,
-                max_health: 6,
-            } }
-            &Archmage => { MinionType {
-                class: Archmage,
-                penalties: vec![IncreasedDamage, ReducedCountdown],
-                types: vec![Caster],
-                max_countdown: 5,
-                start_countdown: 1,
-                max_health: 15,
-            } }
-            &Pope => { MinionType {
-                class: Pope,
-                penalties: vec![IncreasedAmount, IncreasedHealth],
-                types: vec![Divine],
-                max_countdown: 3,
-                start_countdown: 2,
-                max_health: 18,
-            } }
-            &Invincible => { MinionType {
-                class: Invincible,
-                penalties: vec![ReducedCountdown, IncreasedDamage],
-                types: vec![Martial],
-                max_countdown: 7,
-                start_countdown: 7,
-                max_health: 20,
-            } }
-            &General => { MinionType {
-                class: General,
-                penalties: vec![IncreasedHealth, IncreasedAmount],
-                types: vec![Envoy],
-                max_countdown: 4,
-                start_countdown: 4,
-                max_health: 19,
-            } }
-            _ => { panic!() }
-        }
-    }
-
-    fn get_sprite(&self) -> &'static str {
-        match self {
-            Arcanist => "hood.png",
-            Cleric => "hedjet-white-crown.png",
-            Warrior => "light-helm.png",
-            Scout => "robin-hood-hat.png",
-            Pyromancer => "pyromaniac.png",
-            Priest => "spiked-halo.png",
-            Rogue => "cowled.png",
-            Tactician => "warlord-helmet.png",
-            Necromancer => "crowned-skull.png",
-            Oracle => "alien-stare.png",
-            Bulwark => "frog-mouth-helm.png",
-            Sage => "graduate-cap.png",
-            Archmage => "crown.png",
-            Pope => "pope-crown.png",
-            Invincible => "black-knight-helm.png",
-            General => "elf-helmet.png",
-            _ => unreachable!()
-        }
-    }
-}
-
-#[derive(Clone)]
-enum Type {
-    Martial,
-    Caster,
-    Divine,
-    Envoy
+    statuses: Vec<StatusKind>,
 }
 
 #[derive(Component)]
 struct Army {
     health: usize,
     max_health: usize,
-    damage: usize,
-    countdown: usize,
-    max_countdown: usize,
+    statuses: Vec<StatusKind>,
 }
 
+/// Position in the row a minion or army is laid out in, so the layout doesn't depend on query order.
+#[derive(Component)]
+struct Slot(usize);
+
+/// Links a spawned minion or army to its unit in the running `Battle`.
+#[derive(Component)]
+struct BattleId(UnitId);
+
 #[derive(Resource)]
 struct FightTimer(Timer);
 
 #[derive(Component)]
 struct HealthBar();
 
+/// One of the icons under a unit's health bar showing the statuses it has.
+#[derive(Component)]
+struct StatusIcon;
+
 #[derive(Resource, Default, Deref, DerefMut)]
 struct Party(Vec<(MinionType, usize)>);
 
+#[derive(Resource, Default, Deref, DerefMut)]
+struct PartyPurse(Purse);
+
+/// The relic the boss just beaten dropped, shown in the shop after the fight.
+#[derive(Resource)]
+struct BossDrop(Relic);
+
+/// How much power the draft has left to spend on cards.
 #[derive(Resource)]
 struct DraftState {
     power_left: usize,
 }
 
-#[derive(Resource)]
-struct FightState;
+/// The battle being fought.
+#[derive(Resource, Deref, DerefMut)]
+struct FightState {
+    #[deref]
+    battle: Battle,
+    /// The unit of each party member, in party order.
+    party: Vec<UnitId>,
+}
 
 #[derive(Resource)]
 struct DraftSettings {
     power: usize,
+    /// The power a new run starts with.
+    start_power: usize,
+    /// Extra power for the next draft only, for beating a boss or skipping cards.
+    bonus: usize,
+    /// A draft card the player locked and the index it is offered at, to be offered again with the
+    /// next cards.
+    locked: Option<(usize, MinionType)>,
 }
 
+/// Keeps the balance data loaded, so that changes to the files are picked up while playing.
 #[derive(Resource)]
-struct DiscardState;
-
-#[derive(Event)]
-struct DiscardStart;
+#[allow(dead_code)]
+struct BalanceHandles {
+    classes: Handle<ClassFile>,
+    armies: Handle<ArmyFile>,
+    synergies: Handle<SynergyFile>,
+    shop: Handle<ShopFile>,
+}
 
 fn setup(
     mut commands: Commands,
-    mut draft_start: EventWriter<DraftStart>,
+    asset_server: Res<AssetServer>,
 ) {
     let mut camera_bundle = Camera2dBundle::default();
     camera_bundle.projection.scaling_mode = bevy::render::camera::ScalingMode::FixedVertical(100.0);
     commands.spawn(camera_bundle);
 
-    draft_start.send(DraftStart);
+    commands.insert_resource(BalanceHandles {
+        classes: asset_server.load("classes.ron"),
+        armies: asset_server.load("armies.ron"),
+        synergies: asset_server.load("synergies.ron"),
+        shop: asset_server.load("shop.ron"),
+    });
+}
+
+fn menu_start(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section(
+            "Press C to continue your last run, or N to start a new one",
+            TextStyle { font_size: 30.0, ..default() },
+        ).with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(20.0),
+            left: Val::Px(20.0),
+            ..default()
+        }),
+        ContinuePrompt,
+    ));
+}
+
+/// Puts the balance data to use whenever it is loaded or reloaded. Reloaded data is used from the
+/// next draft and the next wave on.
+fn load_balance(
+    mut commands: Commands,
+    mut class_events: EventReader<AssetEvent<ClassFile>>,
+    mut army_events: EventReader<AssetEvent<ArmyFile>>,
+    mut synergy_events: EventReader<AssetEvent<SynergyFile>>,
+    mut shop_events: EventReader<AssetEvent<ShopFile>>,
+    class_lists: Res<Assets<ClassFile>>,
+    army_scalings: Res<Assets<ArmyFile>>,
+    synergy_lists: Res<Assets<SynergyFile>>,
+    shops: Res<Assets<ShopFile>>,
+    classes: Option<Res<Balance<Classes>>>,
+    scaling: Option<Res<Balance<ArmyScaling>>>,
+    synergies: Option<Res<Balance<Synergies>>>,
+    shop: Option<Res<Balance<Shop>>>,
+) {
+    for event in class_events.read() {
+        let (AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id }) = *event else { continue; };
+        let Some(list) = class_lists.get(id) else { continue; };
+        match Classes::new(list) {
+            Ok(loaded) => {
+                if classes.is_some() {
+                    println!("reloaded classes");
+                }
+                commands.insert_resource(Balance(loaded));
+            }
+            Err(err) if classes.is_some() => eprintln!("not reloading classes: {err}"),
+            Err(err) => {
+                eprintln!("could not load classes: {err}");
+                exit(1);
+            }
+        }
+    }
+
+    for event in army_events.read() {
+        let (AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id }) = *event else { continue; };
+        let Some(loaded) = army_scalings.get(id) else { continue; };
+        match loaded.check() {
+            Ok(()) => {
+                if scaling.is_some() {
+                    println!("reloaded army scaling");
+                }
+                commands.insert_resource(Balance(loaded.0.clone()));
+            }
+            Err(err) if scaling.is_some() => eprintln!("not reloading army scaling: {err}"),
+            Err(err) => {
+                eprintln!("could not load army scaling: {err}");
+                exit(1);
+            }
+        }
+    }
+
+    for event in synergy_events.read() {
+        let (AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id }) = *event else { continue; };
+        let Some(loaded) = synergy_lists.get(id) else { continue; };
+        if synergies.is_some() {
+            println!("reloaded synergies");
+        }
+        commands.insert_resource(Balance(loaded.0.clone()));
+    }
+
+    for event in shop_events.read() {
+        let (AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id }) = *event else { continue; };
+        let Some(loaded) = shops.get(id) else { continue; };
+        if shop.is_some() {
+            println!("reloaded shop");
+        }
+        commands.insert_resource(Balance(loaded.0.clone()));
+    }
+}
+
+/// Starts the run once the balance data is loaded, or asks first whether to continue the saved one.
+fn finish_loading(
+    classes: Option<Res<Balance<Classes>>>,
+    scaling: Option<Res<Balance<ArmyScaling>>>,
+    synergies: Option<Res<Balance<Synergies>>>,
+    shop: Option<Res<Balance<Shop>>>,
+    saved: Option<Res<SaveFile>>,
+    mut next_phase: ResMut<NextState<GamePhase>>,
+) {
+    if classes.is_none() || scaling.is_none() || synergies.is_none() || shop.is_none() { return; }
+
+    next_phase.set(match saved {
+        Some(_) => GamePhase::Menu,
+        None => GamePhase::Draft,
+    });
+}
+
+fn menu_tick(
+    mut commands: Commands,
+    saved: Res<SaveFile>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut party: ResMut<Party>,
+    mut stats: ResMut<RunStats>,
+    mut purse: ResMut<PartyPurse>,
+    mut draft_settings: ResMut<DraftSettings>,
+    mut rand: ResMut<Random>,
+    mut recorder: Option<ResMut<Recording>>,
+    save_path: Option<Res<SavePath>>,
+    mut next_phase: ResMut<NextState<GamePhase>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::KeyN) {
+        if let Some(path) = save_path {
+            SaveFile::delete(&path.0);
+        }
+        next_phase.set(GamePhase::Draft);
+    } else if keyboard_input.just_pressed(KeyCode::KeyC) {
+        party.0 = saved.party.clone();
+        *stats = saved.stats.clone();
+        purse.0 = saved.purse.clone();
+        draft_settings.power = saved.power;
+        draft_settings.bonus = saved.bonus;
+        draft_settings.locked = saved.locked.clone();
+        rand.0 = saved.rng.clone();
+        if let (Some(recorder), Some(run)) = (recorder.as_mut(), &saved.replay) {
+            if let Err(err) = recorder.resume(run.clone()) {
+                eprintln!("could not carry on recording: {err}");
+            }
+        }
+        if let Some(run) = &saved.replay {
+            println!("continuing the run with seed {}", run.seed);
+        }
+        next_phase.set(match saved.phase {
+            SavedPhase::Draft { power_left } => {
+                commands.insert_resource(DraftState { power_left });
+                GamePhase::Draft
+            }
+            SavedPhase::Discard => GamePhase::Discard,
+            SavedPhase::Fight => GamePhase::Fight,
+            SavedPhase::Shop => GamePhase::Shop,
+        });
+    }
+}
+
+fn menu_end(
+    mut commands: Commands,
+    prompt: Query<Entity, With<ContinuePrompt>>,
+) {
+    for entity in prompt.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    commands.remove_resource::<SaveFile>();
+}
+
+/// Saves the run as each phase starts.
+fn autosave(
+    mut events: EventReader<Autosave>,
+    path: Option<Res<SavePath>>,
+    party: Res<Party>,
+    stats: Res<RunStats>,
+    purse: Res<PartyPurse>,
+    draft_settings: Res<DraftSettings>,
+    recorder: Option<Res<Recording>>,
+) {
+    let Some(path) = path else {
+        events.clear();
+        return;
+    };
+
+    for event in events.read() {
+        let save = SaveFile::new(
+            party.0.clone(),
+            draft_settings.power,
+            event.phase,
+            event.rng.clone(),
+            recorder.as_ref().map(|recorder| recorder.run().clone()),
+            stats.clone(),
+            purse.clone(),
+            draft_settings.bonus,
+            draft_settings.locked.clone(),
+        );
+        if let Err(err) = save.write(&path.0) {
+            eprintln!("could not save the run: {err}");
+        }
+    }
 }
 
 fn set_health_bars(
-    minions: Query<(Entity, &MinionType, &Minion), Changed<Minion>>, 
+    minions: Query<(Entity, &Class, &Minion), Changed<Minion>>, 
     armies: Query<(Entity, &Army), Changed<Army>>,
     healthbars: Query<&Children>, 
     mut transforms: Query<&mut Transform, With<HealthBar>>,
@@ -334,208 +524,674 @@ fn set_health_bars(
     }
 }
 
+/// Replaces the status icons of units whose statuses may have changed.
+fn set_status_icons(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    minions: Query<(Entity, &Minion), Changed<Minion>>,
+    armies: Query<(Entity, &Army), Changed<Army>>,
+    children: Query<&Children>,
+    icons: Query<(), With<StatusIcon>>,
+) {
+    let changed = minions.iter().map(|(entity, minion)| (entity, &minion.statuses))
+        .chain(armies.iter().map(|(entity, army)| (entity, &army.statuses)));
+    for (entity, statuses) in changed {
+        for child in children.iter_descendants(entity).filter(|&child| icons.contains(child)) {
+            commands.entity(child).despawn_recursive();
+        }
+        let offset = (statuses.len() as f32 - 1.0) / 2.0;
+        commands.entity(entity).with_children(|parent| {
+            for (i, &kind) in statuses.iter().enumerate() {
+                parent.spawn((
+                    SpriteBundle {
+                        transform: Transform {
+                            translation: Vec3::new((i as f32 - offset) * 200.0, -5.5 / 0.01, 0.0),
+                            scale: Vec3::new(0.3, 0.3, 0.0),
+                            rotation: default(),
+                        },
+                        texture: asset_server.load(status_icon(kind)),
+                        ..default()
+                    },
+                    StatusIcon,
+                ));
+            }
+        });
+    }
+}
+
+fn status_kinds(statuses: &Statuses) -> Vec<StatusKind> {
+    statuses.iter().map(|active| active.status.kind).collect()
+}
+
+fn status_icon(kind: StatusKind) -> &'static str {
+    match kind {
+        StatusKind::Burn => "flame.png",
+        StatusKind::Poison => "poison-bottle.png",
+        StatusKind::Stun => "knocked-out-stars.png",
+        StatusKind::Shield => "checked-shield.png",
+        StatusKind::Weakness => "broken-shield.png",
+    }
+}
+
 fn draft_start(
     mut commands: Commands,
-    mut ev: EventReader<DraftStart>,
     mut rand: ResMut<Random>,
     asset_server: Res<AssetServer>,
-    draft_settings: Res<DraftSettings>,
+    mut draft_settings: ResMut<DraftSettings>,
+    state: Option<Res<DraftState>>,
+    classes: Res<Balance<Classes>>,
+    mut autosave: EventWriter<Autosave>,
 ) {
-    if ev.is_empty() { return; }
-    ev.clear();
+    // A continued run may already be part way through the draft, with the bonus spent and maybe
+    // some of the next one's skipped.
+    let power_left = match state {
+        Some(state) => state.power_left,
+        None => draft_settings.power + std::mem::take(&mut draft_settings.bonus),
+    };
 
-    spawn_draft_cards(&mut commands, draft_settings.power.min(16), &mut rand.0, &asset_server);
+    autosave.send(Autosave { phase: SavedPhase::Draft { power_left }, rng: rand.0.clone() });
+    spawn_draft_cards(&mut commands, power_left.min(16), &classes, &mut rand.0, &asset_server, draft_settings.locked.as_ref());
+    spawn_draft_buttons(&mut commands);
 
-    commands.insert_resource(DraftState{ power_left: draft_settings.power });
+    commands.insert_resource(DraftState { power_left });
 }
 
-fn draft_tick(
+/// Spawns a lock button for each card and the reroll and skip buttons, with a reminder of the keys
+/// for them.
+fn spawn_draft_buttons(commands: &mut Commands) {
+    let text_style = TextStyle { font_size: 24.0, ..default() };
+    let locks = [
+        (DraftButton::Lock(0), "Lock 1"),
+        (DraftButton::Lock(1), "Lock 2"),
+        (DraftButton::Lock(2), "Lock 3"),
+    ];
+    let others = [(DraftButton::Reroll, "Reroll (R)"), (DraftButton::Skip, "Skip (S)")];
+    commands.spawn(NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(20.0),
+            width: Val::Percent(100.0),
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::Center,
+            row_gap: Val::Px(8.0),
+            ..default()
+        },
+        ..default()
+    }).insert(DraftButtons).with_children(|parent| {
+        for buttons in [&locks[..], &others[..]] {
+            parent.spawn(NodeBundle {
+                style: Style {
+                    column_gap: Val::Px(16.0),
+                    ..default()
+                },
+                ..default()
+            }).with_children(|row| {
+                for &(button, label) in buttons {
+                    row.spawn((
+                        ButtonBundle {
+                            style: Style {
+                                padding: UiRect::axes(Val::Px(16.0), Val::Px(8.0)),
+                                ..default()
+                            },
+                            background_color: Color::DARK_GRAY.into(),
+                            ..default()
+                        },
+                        button,
+                    )).with_children(|button| {
+                        button.spawn(TextBundle::from_section(label, text_style.clone()));
+                    });
+                }
+            });
+        }
+        parent.spawn(TextBundle::from_section(
+            "Click or press 1-3 to pick a card, right click or shift+1-3 to lock it for the next cards",
+            TextStyle { font_size: 18.0, ..default() },
+        ));
+    });
+}
+
+fn draft_end(
     mut commands: Commands,
-    state: Option<ResMut<DraftState>>,
+    cards: Query<Entity, Or<(With<DraftCardIndex>, With<DraftButtons>)>>,
+) {
+    for entity in cards.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    commands.remove_resource::<DraftState>();
+}
+
+fn read_input(
+    phase: Res<State<GamePhase>>,
+    replay: Option<Res<Replay>>,
     mut mouse_input: EventReader<MouseButtonInput>,
-    mut party: ResMut<Party>,
-    minions: Query<(Entity, &MinionType, &DraftCardIndex)>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
     windows: Query<&Window>,
-    mut rand: ResMut<Random>,
-    asset_server: Res<AssetServer>,
     camera: Query<(&Camera, &GlobalTransform)>,
-    mut discard_start: EventWriter<DiscardStart>,
+    buttons: Query<(&Interaction, &DraftButton), Changed<Interaction>>,
+    mut decisions: EventWriter<Decided>,
 ) {
-    let Some(mut state) = state else { return; };
+    if replay.is_some() {
+        mouse_input.clear();
+        return;
+    }
 
     for event in mouse_input.read() {
-        if event.button != MouseButton::Left || event.state != ButtonState::Pressed {
+        if *phase.get() != GamePhase::Draft || event.state != ButtonState::Pressed {
             continue;
         }
         let window = windows.get(event.window).unwrap();
         let pos = window.cursor_position().unwrap_or_default();
         let (camera, camera_transform) = camera.single();
-        let x = camera.viewport_to_world_2d(camera_transform, pos).unwrap().x;
-        let mut entities: Vec<_> = minions.iter().collect();
-        assert_eq!(entities.len(), 3);
-        entities.sort_by_key(|m| m.2.0);
-        let index = if x < -20.0 {
+        let pos = camera.viewport_to_world_2d(camera_transform, pos).unwrap();
+        // Clicks away from the cards, like on the buttons, don't pick one.
+        if pos.y.abs() > 15.0 {
+            continue;
+        }
+        let index = if pos.x < -20.0 {
             0
-        } else if x < 20.0 {
+        } else if pos.x < 20.0 {
             1
         } else {
             2
         };
-        party.push((entities[index].1.clone(), 0));
-        for (entity, _, _) in minions.iter() {
-            commands.entity(entity).despawn_recursive();
+        match event.button {
+            MouseButton::Left => { decisions.send(Decided(Decision::Draft(index))); }
+            MouseButton::Right => { decisions.send(Decided(Decision::Lock(index))); }
+            _ => {}
         }
-        state.power_left -= state.power_left.min(16);
+    }
+
+    let number_keys = [
+        KeyCode::Digit1, KeyCode::Digit2, KeyCode::Digit3, KeyCode::Digit4, KeyCode::Digit5,
+        KeyCode::Digit6, KeyCode::Digit7, KeyCode::Digit8, KeyCode::Digit9, KeyCode::Digit0,
+    ];
 
-        if state.power_left == 0 && party.len() < 3 {
-            state.power_left = 1;
-        } else if state.power_left == 0 {
-            discard_start.send(DiscardStart);
-            commands.remove_resource::<DraftState>();
-            return;
+    let shift = keyboard_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+
+    if *phase.get() == GamePhase::Draft {
+        for (index, key) in number_keys.into_iter().take(3).enumerate() {
+            if keyboard_input.just_pressed(key) {
+                decisions.send(Decided(if shift { Decision::Lock(index) } else { Decision::Draft(index) }));
+            }
+        }
+        if keyboard_input.just_pressed(KeyCode::KeyR) {
+            decisions.send(Decided(Decision::Reroll));
         }
-        spawn_draft_cards(&mut commands, state.power_left.min(16), &mut rand.0, &asset_server);
+        if keyboard_input.just_pressed(KeyCode::KeyS) {
+            decisions.send(Decided(Decision::Skip));
+        }
+        for (interaction, button) in buttons.iter() {
+            if *interaction != Interaction::Pressed { continue; }
+            decisions.send(Decided(match *button {
+                DraftButton::Lock(index) => Decision::Lock(index),
+                DraftButton::Reroll => Decision::Reroll,
+                DraftButton::Skip => Decision::Skip,
+            }));
+        }
+    }
+
+    if *phase.get() == GamePhase::Shop {
+        for (index, key) in number_keys.into_iter().enumerate() {
+            if keyboard_input.just_pressed(key) {
+                decisions.send(Decided(Decision::Buy(index)));
+            }
+        }
+        if keyboard_input.just_pressed(KeyCode::Enter) {
+            decisions.send(Decided(Decision::Confirm));
+        }
+    }
+
+    if *phase.get() != GamePhase::Discard { return; }
+
+    // With shift held, the number moves that minion forward instead, with control held it upgrades
+    // it, with alt held it merges it with two copies of it and with E held it equips it.
+    let control = keyboard_input.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    let alt = keyboard_input.any_pressed([KeyCode::AltLeft, KeyCode::AltRight]);
+    let equip = keyboard_input.pressed(KeyCode::KeyE);
+    for (index, key) in number_keys.into_iter().enumerate() {
+        if keyboard_input.just_pressed(key) {
+            decisions.send(Decided(if shift {
+                Decision::Forward(index)
+            } else if control {
+                Decision::Upgrade(index)
+            } else if alt {
+                Decision::Merge(index)
+            } else if equip {
+                Decision::Equip(index)
+            } else {
+                Decision::Discard(index)
+            }));
+        }
+    }
+
+    if keyboard_input.just_pressed(KeyCode::Enter) {
+        decisions.send(Decided(Decision::Confirm));
+    }
+}
+
+/// Feeds the decisions of a replay to the game, one per frame, each as soon as the game is in a
+/// state where it can be made.
+fn replay_tick(
+    mut commands: Commands,
+    replay: Option<ResMut<Replay>>,
+    phase: Res<State<GamePhase>>,
+    mut decisions: EventWriter<Decided>,
+) {
+    let Some(mut replay) = replay else { return; };
+
+    let Some(&decision) = replay.front() else {
+        println!("replay finished");
+        commands.remove_resource::<Replay>();
+        return;
+    };
+
+    let ready = match decision {
+        Decision::Draft(_) | Decision::Reroll | Decision::Skip | Decision::Lock(_) => *phase.get() == GamePhase::Draft,
+        Decision::Discard(_) | Decision::Forward(_) | Decision::Upgrade(_) | Decision::Merge(_) | Decision::Equip(_) => {
+            *phase.get() == GamePhase::Discard
+        }
+        Decision::Buy(_) => *phase.get() == GamePhase::Shop,
+        Decision::Confirm => matches!(*phase.get(), GamePhase::Discard | GamePhase::Shop),
+    };
+    if ready {
+        decisions.send(Decided(decision));
+        replay.pop_front();
+    }
+}
+
+fn draft_tick(
+    mut commands: Commands,
+    mut state: ResMut<DraftState>,
+    mut decisions: EventReader<Decided>,
+    mut party: ResMut<Party>,
+    mut stats: ResMut<RunStats>,
+    minions: Query<(Entity, &Class, &DraftCardIndex)>,
+    lock_icons: Query<Entity, With<LockIcon>>,
+    mut rand: ResMut<Random>,
+    asset_server: Res<AssetServer>,
+    mut next_phase: ResMut<NextState<GamePhase>>,
+    mut recorder: Option<ResMut<Recording>>,
+    classes: Res<Balance<Classes>>,
+    shop: Res<Balance<Shop>>,
+    mut purse: ResMut<PartyPurse>,
+    mut draft_settings: ResMut<DraftSettings>,
+    mut autosave: EventWriter<Autosave>,
+) {
+    if decisions.is_empty() { return; }
+    let mut cards: Vec<_> = minions.iter().collect();
+    assert_eq!(cards.len(), 3);
+    cards.sort_by_key(|m| m.2.0);
+
+    for &Decided(decision) in decisions.read() {
+        match decision {
+            Decision::Draft(index) if index < cards.len() => {
+                party.push((cards[index].1.0.clone(), 0));
+                stats.drafted();
+                if draft_settings.locked.as_ref().is_some_and(|(locked, _)| *locked == index) {
+                    draft_settings.locked = None;
+                }
+            }
+            Decision::Reroll => {
+                if purse.rerolls > 0 {
+                    purse.rerolls -= 1;
+                } else if purse.gold >= shop.reroll {
+                    purse.gold -= shop.reroll;
+                } else {
+                    continue;
+                }
+            }
+            // The party has to be filled first, or the power refilled to get it there could be
+            // skipped over and over.
+            Decision::Skip if party.len() >= 3 => draft_settings.bonus += state.power_left.min(16),
+            Decision::Lock(index) if index < cards.len() => {
+                let unlock = draft_settings.locked.as_ref().is_some_and(|(locked, _)| *locked == index);
+                draft_settings.locked = if unlock { None } else { Some((index, cards[index].1.0.clone())) };
+                for entity in lock_icons.iter() {
+                    commands.entity(entity).despawn_recursive();
+                }
+                if !unlock {
+                    let card = cards[index].0;
+                    commands.entity(card).with_children(|parent| { spawn_lock_icon(parent, &asset_server); });
+                }
+                if let Some(recorder) = recorder.as_mut() {
+                    recorder.record(decision);
+                }
+                continue;
+            }
+            _ => continue,
+        }
+        if let Some(recorder) = recorder.as_mut() {
+            recorder.record(decision);
+        }
+        for (entity, _, _) in cards.iter() {
+            commands.entity(*entity).despawn_recursive();
+        }
+
+        // Picking or skipping spends the cards' power, rerolling deals new ones for the same power.
+        if decision != Decision::Reroll {
+            state.power_left -= state.power_left.min(16);
+            if state.power_left == 0 && party.len() < 3 {
+                state.power_left = 1;
+            } else if state.power_left == 0 && purse.extra_picks > 0 {
+                purse.extra_picks -= 1;
+                state.power_left = draft_settings.power.min(16);
+            } else if state.power_left == 0 {
+                next_phase.set(GamePhase::Discard);
+                return;
+            }
+        }
+        autosave.send(Autosave { phase: SavedPhase::Draft { power_left: state.power_left }, rng: rand.0.clone() });
+        let locked = draft_settings.locked.as_ref();
+        spawn_draft_cards(&mut commands, state.power_left.min(16), &classes, &mut rand.0, &asset_server, locked);
+        // The new cards are not spawned until the next frame, so the rest have to wait for them.
+        return;
     }
 }
 
 fn spawn_draft_cards(
     commands: &mut Commands,
     power: usize,
-    rand: &mut StdRng,
+    classes: &Classes,
+    rand: &mut ChaCha12Rng,
     asset_server: &AssetServer,
+    locked: Option<&(usize, MinionType)>,
 ) {
     let spacing = 40.0;
 
     let count = 3;
     for i in 0..3 {
-        let class = generate_class(power, rand);
-        let image = asset_server.load(class.get_sprite());
-        commands.spawn((
+        let (class, is_locked) = match locked {
+            Some((index, class)) if *index == i => (class.clone(), true),
+            _ => (classes.generate(power, rand), false),
+        };
+        let image = asset_server.load(class.sprite.clone());
+        let card = commands.spawn((
             SpriteBundle {
                 transform: Transform {
-                    translation: Vec3::new((spacing * (count - 1) as f32 * 0.5) + (i as f32 * -spacing), 0.0, 0.0),
+                    // Counted from the left, the same as clicks.
+                    translation: Vec3::new((i as f32 - (count - 1) as f32 * 0.5) * spacing, 0.0, 0.0),
                     scale: Vec3::new(0.05, 0.05, 0.0),
                     rotation: default(),
                 },
                 texture: image,
                 ..default()
             },
-            class.get_type(),
+            Class(class),
             DraftCardIndex(i),
-        ));
+        )).id();
+        if is_locked {
+            commands.entity(card).with_children(|parent| { spawn_lock_icon(parent, asset_server); });
+        }
     }
 }
 
+fn spawn_lock_icon(parent: &mut ChildBuilder, asset_server: &AssetServer) {
+    parent.spawn((
+        SpriteBundle {
+            transform: Transform {
+                translation: Vec3::new(0.0, 300.0, 0.1),
+                scale: Vec3::new(0.4, 0.4, 0.0),
+                rotation: default(),
+            },
+            texture: asset_server.load("padlock.png"),
+            ..default()
+        },
+        LockIcon,
+    ));
+}
+
 fn discard_start(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     party: Res<Party>,
-    mut ev: EventReader<DiscardStart>,
+    rand: Res<Random>,
+    synergies: Res<Balance<Synergies>>,
+    classes: Res<Balance<Classes>>,
+    purse: Res<PartyPurse>,
+    mut autosave: EventWriter<Autosave>,
 ) {
-    if ev.is_empty() { return; }
-    ev.clear();
+    autosave.send(Autosave { phase: SavedPhase::Discard, rng: rand.0.clone() });
+    spawn_synergy_panel(&mut commands, &synergies, &party);
+    commands.spawn((
+        TextBundle::from_section(bag_text(&purse), TextStyle { font_size: 20.0, ..default() })
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(12.0),
+                right: Val::Px(12.0),
+                ..default()
+            }),
+        BagPanel,
+    ));
+
+    spawn_party(&mut commands, &asset_server, &classes, &party);
+}
+
+/// Spawns the party for the discard, marking the members that can be upgraded or merged.
+fn spawn_party(commands: &mut Commands, asset_server: &AssetServer, classes: &Classes, party: &Party) {
+    for (i, (minion, bounty)) in party.iter().enumerate() {
+        let entity = spawn_minion(commands, asset_server, minion, minion.max_health);
+        commands.entity(entity).insert(Slot(i));
+
+        let mut offers = vec![];
+        if classes.upgrade(minion, *bounty).is_some() {
+            offers.push(("upgrade.png", Color::rgb(0.3, 1.0, 0.3)));
+        }
+        if copies(party, i).is_some() && classes.merge(minion).is_some() {
+            offers.push(("star-formation.png", Color::rgb(1.0, 0.84, 0.0)));
+        }
+        let offset = (offers.len() as f32 - 1.0) / 2.0;
+        commands.entity(entity).with_children(|parent| {
+            for (i, (icon, color)) in offers.into_iter().enumerate() {
+                parent.spawn(SpriteBundle {
+                    transform: Transform {
+                        translation: Vec3::new((i as f32 - offset) * 250.0, 7.0 / 0.01, 0.0),
+                        scale: Vec3::new(0.4, 0.4, 0.0),
+                        rotation: default(),
+                    },
+                    texture: asset_server.load(icon),
+                    sprite: Sprite { color, ..default() },
+                    ..default()
+                });
+            }
+        });
+    }
+}
 
-    for (minion, _bounty) in party.iter() {
-        spawn_minion(&mut commands, &asset_server, minion);
+fn bag_text(purse: &Purse) -> String {
+    let names = |names: Vec<&str>| if names.is_empty() { "none".to_string() } else { names.join(", ") };
+    let relics = names(purse.relics.iter().map(|relic| relic.name.as_str()).collect());
+    let items = names(purse.items.iter().map(|item| item.name.as_str()).collect());
+    let mut text = format!("Relics: {relics}\nItems: {items}");
+    if !purse.items.is_empty() {
+        text += "\nHold E and press a number to equip the first item";
     }
+    text
+}
 
-    commands.insert_resource(DiscardState);
+/// The two party members that would be merged into the one at `index`: the first other copies of
+/// its class with as many stars.
+fn copies(party: &Party, index: usize) -> Option<[usize; 2]> {
+    let (minion, _) = party.get(index)?;
+    let mut copies = (0..party.len()).filter(|&i| {
+        i != index && party[i].0.name == minion.name && party[i].0.stars == minion.stars
+    });
+    Some([copies.next()?, copies.next()?])
 }
 
 fn discard_tick(
     mut commands: Commands,
-    state: Option<Res<DiscardState>>,
-    mut fight_start: EventWriter<FightPreload>,
-    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut next_phase: ResMut<NextState<GamePhase>>,
+    mut decisions: EventReader<Decided>,
     mut party: ResMut<Party>,
-    minions: Query<Entity, With<MinionType>>,
-    mut redraw: EventWriter<FightStart>,
+    mut stats: ResMut<RunStats>,
+    minions: Query<Entity, With<Minion>>,
+    mut recorder: Option<ResMut<Recording>>,
+    synergies: Res<Balance<Synergies>>,
+    classes: Res<Balance<Classes>>,
+    asset_server: Res<AssetServer>,
+    mut purse: ResMut<PartyPurse>,
+    mut panel: Query<&mut Text, With<SynergyPanel>>,
+    mut bag: Query<&mut Text, (With<BagPanel>, Without<SynergyPanel>)>,
 ) {
-    if state.is_none() { return; }
-
-    redraw.send(FightStart);
-
-    if keyboard_input.just_pressed(KeyCode::Digit1) {
-        party.remove(0);
-    }
-    if keyboard_input.just_pressed(KeyCode::Digit2) {
-        party.remove(1);
-    }
-    if keyboard_input.just_pressed(KeyCode::Digit3) {
-        party.remove(2);
-    }
-    if keyboard_input.just_pressed(KeyCode::Digit4) {
-        party.remove(3);
-    }
-    if keyboard_input.just_pressed(KeyCode::Digit5) {
-        party.remove(4);
-    }
-    if keyboard_input.just_pressed(KeyCode::Digit6) {
-        party.remove(5);
-    }
-    if keyboard_input.just_pressed(KeyCode::Digit7) {
-        party.remove(6);
-    }
-    if keyboard_input.just_pressed(KeyCode::Digit8) {
-        party.remove(7);
-    }
-    if keyboard_input.just_pressed(KeyCode::Digit9) {
-        party.remove(8);
-    }
-    if keyboard_input.just_pressed(KeyCode::Digit0) {
-        party.remove(9);
+    let mut changed = false;
+    for &Decided(decision) in decisions.read() {
+        match decision {
+            Decision::Discard(index) if index < party.len() => {
+                let (minion, _) = party.remove(index);
+                purse.items.extend(minion.item);
+                stats.discarded(index);
+            }
+            Decision::Forward(index) if index > 0 && index < party.len() => {
+                party.swap(index - 1, index);
+                stats.moved_forward(index);
+            }
+            Decision::Upgrade(index) if index < party.len() => {
+                let (minion, bounty) = &mut party[index];
+                let Some(upgraded) = classes.upgrade(minion, *bounty) else { continue; };
+                *minion = upgraded;
+            }
+            Decision::Merge(index) => {
+                let Some(copies) = copies(&party, index) else { continue; };
+                let Some(merged) = classes.merge(&party[index].0) else { continue; };
+                // The merged minion carries on with the highest bounty of the three.
+                let bounty = copies.iter().map(|&copy| party[copy].1).fold(party[index].1, usize::max);
+                party[index] = (merged, bounty);
+                let mut index = index;
+                for copy in copies.into_iter().rev() {
+                    let (minion, _) = party.remove(copy);
+                    purse.items.extend(minion.item);
+                    stats.merged(copy, index);
+                    if copy < index {
+                        index -= 1;
+                    }
+                }
+            }
+            // The item it carried, if any, goes back in the bag.
+            Decision::Equip(index) if index < party.len() && !purse.items.is_empty() => {
+                let item = purse.items.remove(0);
+                purse.items.extend(party[index].0.item.replace(item));
+            }
+            Decision::Confirm => next_phase.set(GamePhase::Fight),
+            _ => continue,
+        }
+        changed |= decision != Decision::Confirm;
+        if let Some(recorder) = recorder.as_mut() {
+            recorder.record(decision);
+        }
+        if decision == Decision::Confirm {
+            break;
+        }
     }
 
-    if keyboard_input.just_pressed(KeyCode::Enter) {
+    // Respawn the whole party, so the slots match it and the offers to upgrade or merge stay right.
+    if changed {
         for entity in minions.iter() {
             commands.entity(entity).despawn_recursive();
         }
-        fight_start.send(FightPreload);
-        commands.remove_resource::<DiscardState>();
+        spawn_party(&mut commands, &asset_server, &classes, &party);
+        for mut text in panel.iter_mut() {
+            text.sections[0].value = synergy_text(&synergies, &party);
+        }
+        for mut text in bag.iter_mut() {
+            text.sections[0].value = bag_text(&purse);
+        }
     }
 }
 
-fn fight_start_pre(
+fn discard_end(
+    mut commands: Commands,
+    minions: Query<Entity, Or<(With<Minion>, With<SynergyPanel>, With<BagPanel>)>>,
+) {
+    for entity in minions.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn fight_start(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
-    mut ev: EventReader<FightPreload>,
-    mut evw: EventWriter<FightStart>,
+    mut rand: ResMut<Random>,
     draft_settings: Res<DraftSettings>,
     party: Res<Party>,
+    scaling: Res<Balance<ArmyScaling>>,
+    synergies: Res<Balance<Synergies>>,
+    purse: Res<PartyPurse>,
+    mut autosave: EventWriter<Autosave>,
 ) {
-    if ev.is_empty() { return; }
-    ev.clear();
-    let difficulty = draft_settings.power;
-    let mut health = difficulty;
-    let mut amount = difficulty / 5;
-    let mut countdown = difficulty / 5;
-    let mut damage = difficulty / 2;
-
-    for (minion, bounty) in party.iter() {
-        spawn_minion(&mut commands, &asset_server, minion);
-        if difficulty % 5 == 0 {
-            for penalty in &minion.penalties {
-                match penalty {
-                    IncreasedAmount => amount += bounty / 2,
-                    IncreasedDamage => damage += bounty,
-                    IncreasedHealth => health += bounty * 2,
-                    ReducedCountdown => countdown += bounty / 2,
-                }
-            }
-        }
+    autosave.send(Autosave { phase: SavedPhase::Fight, rng: rand.0.clone() });
+    let mut battle = Battle::start(draft_settings.power, party.as_slice(), &scaling, &synergies, &purse.relics, rand.gen());
+    for minion in battle.minions.iter_mut() {
+        minion.health += purse.heal;
     }
+    spawn_synergy_panel(&mut commands, &synergies, &party);
+    // The party is all there is when the fight starts, so its units are the minions in order.
+    let party = battle.minions.iter().map(|minion| minion.id).collect();
 
-    for i in 0..(amount+1) {
-        spawn_army(&mut commands, &asset_server, health, damage, countdown);
+    for minion in battle.minions.iter() {
+        let entity = spawn_minion(&mut commands, &asset_server, &minion.kind, minion.health);
+        commands.entity(entity).insert((BattleId(minion.id), Slot(minion.id as usize)));
     }
 
-    commands.insert_resource(FightState);
+    for army in battle.armies.iter() {
+        let boss = battle.boss.as_ref().is_some_and(|boss| boss.id == army.id);
+        spawn_army(&mut commands, &asset_server, army, boss);
+    }
 
-    evw.send(FightStart);
+    commands.insert_resource(FightState { battle, party });
+}
+
+fn fight_end(
+    mut commands: Commands,
+    units: Query<Entity, Or<(With<BattleId>, With<SynergyPanel>)>>,
+    mut purse: ResMut<PartyPurse>,
+) {
+    for entity in units.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    // Only used up here, so that the save written as the fight starts still has it.
+    purse.heal = 0;
+    commands.remove_resource::<FightState>();
 }
 
-fn spawn_minion(commands: &mut Commands, asset_server: &AssetServer, minion: &MinionType) {
-    let texture = asset_server.load(minion.class.get_sprite());
+/// Lists the party's synergies in the corner, for the discard and the fight.
+fn spawn_synergy_panel(commands: &mut Commands, synergies: &Synergies, party: &Party) {
+    commands.spawn((
+        TextBundle::from_section(synergy_text(synergies, party), TextStyle { font_size: 20.0, ..default() })
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(12.0),
+                left: Val::Px(12.0),
+                ..default()
+            }),
+        SynergyPanel,
+    ));
+}
+
+fn synergy_text(synergies: &Synergies, party: &Party) -> String {
+    let mut lines = vec![];
+    for status in synergies.status(party) {
+        let synergy = status.synergy;
+        let mut line = format!("{:?} {}", synergy.of, status.count);
+        if let Some(level) = status.level {
+            let bonus = match synergy.bonus {
+                Bonus::Damage => format!("+{} damage", level.amount),
+                Bonus::Healing => format!("+{} healing", level.amount),
+                Bonus::Health => format!("+{} health", level.amount),
+                Bonus::Countdown => format!("-{} countdown", level.amount),
+            };
+            line += &format!(": {bonus}");
+        }
+        let next = synergy.levels.iter().map(|level| level.count).filter(|&count| count > status.count).min();
+        if let Some(next) = next {
+            line += &format!(" (next at {next})");
+        }
+        lines.push(line);
+    }
+    lines.join("\n")
+}
+
+fn spawn_minion(commands: &mut Commands, asset_server: &AssetServer, minion: &MinionType, health: usize) -> Entity {
+    let texture = asset_server.load(minion.sprite.clone());
     commands.spawn((
         SpriteBundle {
             transform: Transform {
@@ -549,8 +1205,11 @@ fn spawn_minion(commands: &mut Commands, asset_server: &AssetServer, minion: &Mi
             },
             ..default()
         },
-        minion.clone(),
-        minion.get_minion(),
+        Class(minion.clone()),
+        Minion {
+            health,
+            statuses: vec![],
+        },
     )).with_children(|parent| {
         parent.spawn((
             SpriteBundle {
@@ -567,19 +1226,59 @@ fn spawn_minion(commands: &mut Commands, asset_server: &AssetServer, minion: &Mi
             },
             HealthBar(),
         ));
-    });
+        if let Some(item) = &minion.item {
+            parent.spawn(SpriteBundle {
+                transform: Transform {
+                    translation: Vec3::new(-2.8 / 0.01, 2.0 / 0.01, 0.0),
+                    scale: Vec3::new(0.3, 0.3, 0.0),
+                    rotation: default(),
+                },
+                texture: asset_server.load(item.icon.clone()),
+                ..default()
+            });
+        }
+        for i in 0..minion.stars {
+            parent.spawn(SpriteBundle {
+                transform: Transform {
+                    translation: Vec3::new(2.8 / 0.01, (2.0 - i as f32 * 1.2) / 0.01, 0.0),
+                    scale: Vec3::new(0.2, 0.2, 0.0),
+                    rotation: default(),
+                },
+                texture: asset_server.load("round-star.png"),
+                sprite: Sprite {
+                    color: Color::rgb(1.0, 0.84, 0.0),
+                    ..default()
+                },
+                ..default()
+            });
+        }
+        if minion.taunt {
+            parent.spawn(SpriteBundle {
+                transform: Transform {
+                    translation: Vec3::new(0.0, 4.0 / 0.01, 0.0),
+                    scale: Vec3::new(0.4, 0.4, 0.0),
+                    rotation: default(),
+                },
+                texture: asset_server.load("shouting.png"),
+                sprite: Sprite {
+                    color: Color::rgb(1.0, 0.6, 0.0),
+                    ..default()
+                },
+                ..default()
+            });
+        }
+    }).id()
 }
 
-fn fight_start(
-    mut commands: Commands,
-    mut minions: Query<(&mut Transform, &MinionType, &mut Minion), Without<Army>>, 
-    mut armies: Query<&mut Transform, With<Army>>, 
-    mut ev: EventReader<FightStart>,
+/// Lays out the minions in a row at the bottom and the armies in a row at the top, in slot order.
+/// The front line of each side stands a little closer to the other.
+fn arrange_units(
+    mut minions: Query<(&mut Transform, &Slot), (With<Minion>, Without<Army>)>,
+    mut armies: Query<(&mut Transform, &Slot), With<Army>>,
 ) {
-    if ev.is_empty() { return; }
-    ev.clear();
-
-    let m_iterator = minions.iter_mut();
+    let mut m_sorted: Vec<_> = minions.iter_mut().collect();
+    m_sorted.sort_by_key(|m| m.1.0);
+    let m_iterator = m_sorted.into_iter().map(|m| m.0);
     let m_count = m_iterator.len();
     let m_spacing = if m_count > 1 {
         (80.0 / (m_count - 1) as f32).min(10.0)
@@ -587,16 +1286,18 @@ fn fight_start(
         0.0
     };
 
-    for (i, (mut p, t, mut m)) in m_iterator.enumerate() {
+    for (i, mut p) in m_iterator.enumerate() {
         if m_count == 1 {
             p.translation.x = 0.0;
         } else {
             p.translation.x = (m_spacing * (m_count - 1) as f32 * 0.5) + (i as f32 * -m_spacing);
         }
-        p.translation.y = -40.0;
+        p.translation.y = if i < FRONT_LINE { -34.0 } else { -40.0 };
     }
 
-    let a_iterator = armies.iter_mut();
+    let mut a_sorted: Vec<_> = armies.iter_mut().collect();
+    a_sorted.sort_by_key(|a| a.1.0);
+    let a_iterator = a_sorted.into_iter().map(|a| a.0);
     let a_count = a_iterator.len();
     let mut a_spacing = 0.0;
     if a_count > 1 {
@@ -609,261 +1310,335 @@ fn fight_start(
         } else {
             p.translation.x = (a_spacing * (a_count - 1) as f32 * 0.5) + (i as f32 * -a_spacing);
         }
-        p.translation.y = 40.0;
+        p.translation.y = if i < FRONT_LINE { 34.0 } else { 40.0 };
     }
 }
 
 fn fight_tick(
     mut commands: Commands,
-    mut minions: Query<(Entity, &MinionType, &mut Minion), Without<Army>>,
-    mut armies: Query<(Entity, &mut Army)>,
+    mut minions: Query<(Entity, &BattleId, &mut Minion), Without<Army>>,
+    mut armies: Query<(Entity, &BattleId, &mut Army)>,
     mut timer: ResMut<FightTimer>,
     mut party: ResMut<Party>,
+    mut stats: ResMut<RunStats>,
     time: Res<Time>,
-    mut rand: ResMut<Random>,
-    state: Option<Res<FightState>>,
-    mut draft_start: EventWriter<DraftStart>,
-    mut redraw: EventWriter<FightStart>,
+    mut battle: ResMut<FightState>,
+    mut next_phase: ResMut<NextState<GamePhase>>,
     mut draft_settings: ResMut<DraftSettings>,
     asset_server: Res<AssetServer>,
+    scaling: Res<Balance<ArmyScaling>>,
+    shop: Res<Balance<Shop>>,
+    mut purse: ResMut<PartyPurse>,
+    mut rand: ResMut<Random>,
+    mut combat_events: CombatEvents,
 ) {
-    if state.is_none() {
-        return
-    }
-
     if !timer.0.tick(time.delta()).just_finished() { return; }
 
-    redraw.send(FightStart);
+    let outcome = battle.step();
 
-    let a_iterator = armies.iter_mut();
-    let mut a_count: usize = a_iterator.len();
-    if a_count == 0 {
-        draft_settings.power += 1;
-        draft_start.send(DraftStart);
-        commands.remove_resource::<FightState>();
-        for (entity, _, _) in minions.iter() {
-            commands.entity(entity).despawn_recursive();
-        }
-        for (_, bounty) in party.iter_mut() {
-            *bounty += 1;
+    let mut entities = HashMap::new();
+    for (entity, id, mut minion) in minions.iter_mut() {
+        entities.insert(id.0, entity);
+        if let Some(m) = battle.minion(id.0) {
+            minion.health = m.health;
+            minion.statuses = status_kinds(&m.statuses);
         }
-        return;
     }
 
-    let mut army_attacks: Vec<usize> = vec!();
-    let mut minion_heals: Vec<usize> = vec!();
-    let mut minion_aoe_heals: Vec<usize> = vec!();
-    let mut minion_aoe_overheals: Vec<usize> = vec!();
-    let mut minion_reductions: Vec<usize> = vec!();
-    let mut minion_aoe_reductions: Vec<usize> = vec!();
-    let mut army_entities = vec![];
-    for (e, mut a) in a_iterator {
-        a.countdown -= 1;
-        if a.countdown == 0 {
-            a.countdown = a.max_countdown;
-            army_attacks.push(a.damage);
+    for (entity, id, mut army) in armies.iter_mut() {
+        entities.insert(id.0, entity);
+        if let Some(a) = battle.army(id.0) {
+            army.health = a.health;
+            army.statuses = status_kinds(&a.statuses);
         }
-        army_entities.push(e);
     }
 
-    let mut minion_entities = vec!();
-
-    let m_iterator = minions.iter_mut();
-    let mut m_count = m_iterator.len();
-    if m_count == 0 { return; }
-
-    for (e, m, mut o) in m_iterator {
-        minion_entities.push(e);
-        if a_count == 0 {
-            return;
-        }
-        if o.health == 0 {
+    // Armies that are called in during the fight are never the boss.
+    for army in battle.armies.iter() {
+        if entities.contains_key(&army.id) {
             continue;
         }
-        if o.countdown > 0 {
-            o.countdown -= 1;
-        }
-        if o.countdown > 0 {
+        let entity = spawn_army(&mut commands, &asset_server, army, false);
+        entities.insert(army.id, entity);
+    }
+
+    for minion in battle.minions.iter() {
+        if entities.contains_key(&minion.id) {
             continue;
         }
-        o.countdown = m.max_countdown;
-        match m.class {
-            Arcanist => {
-                let target = army_entities[rand.gen_range(0..a_count)];
-                let mut army = armies.get_mut(target).unwrap();
-                if army.1.health <= 3 {
-                    commands.entity(target).despawn_recursive();
-                    a_count -= 1;
-                } else {
-                    army.1.health -= 3;
-                }
-            }
-            Cleric => {
-                minion_heals.push(2);
-            }
-            Warrior => {
-                let target = army_entities[rand.gen_range(0..a_count)];
-                let mut army = armies.get_mut(target).unwrap();
-                if army.1.health <= 2 {
-                    commands.entity(target).despawn_recursive();
-                    a_count -= 1;
-                } else {
-                    army.1.health -= 2;
-                }
-            }
-            Scout => {
-                minion_reductions.push(1);
-            }
-            Pyromancer => {
-                for mut army in armies.iter_mut() {
-                    if army.1.health <= 2 {
-                        commands.entity(army.0).despawn_recursive();
-                        a_count -= 1;
-                    } else {
-                        army.1.health -= 2;
-                    }
-                }
-            }
-            Priest => {
-                minion_heals.push(1);
-            }
-            Rogue => {
-                let target = army_entities[rand.gen_range(0..a_count)];
-                let mut army = armies.get_mut(target).unwrap();
-                if army.1.health <= 5 {
-                    commands.entity(target).despawn_recursive();
-                    a_count -= 1;
-                } else {
-                    army.1.health -= 5;
-                }
-            }
-            Tactician => {
-                minion_reductions.push(3);
-            }
-            Necromancer => {
-                spawn_minion(&mut commands, &asset_server, &Warrior.get_type());
-            }
-            Oracle => {
-                minion_heals.push(1);
-            }
-            Bulwark => {
-                minion_aoe_overheals.push(1);   
-            }
-            Sage => {
-                let target = army_entities[rand.gen_range(0..a_count)];
-                let mut army = armies.get_mut(target).unwrap();
-                army.1.countdown += 3;
-            }
-            Archmage => {
-                for mut army in armies.iter_mut() {
-                    if army.1.health <= 1 {
-                        commands.entity(army.0).despawn_recursive();
-                        a_count -= 1;
-                    } else {
-                        army.1.health -= 1;
-                    }
+        let entity = spawn_minion(&mut commands, &asset_server, &minion.kind, minion.health);
+        commands.entity(entity).insert((BattleId(minion.id), Slot(minion.id as usize)));
+        entities.insert(minion.id, entity);
+    }
+
+    let events = battle.take_events();
+    combat_events.send(events, |id| entities.get(&id).copied());
+
+    match outcome {
+        Outcome::Victory => {
+            stats.record_fight(&battle, &battle.party);
+            stats.rounds += 1;
+            let survivors = battle.party.iter().filter(|id| battle.minion(**id).is_some()).count();
+            purse.gold += shop.reward.gold(draft_settings.power, survivors);
+            draft_settings.power += 1;
+            if battle.boss.is_some() {
+                draft_settings.bonus += scaling.boss.reward;
+                if let Some(relic) = shop.relic(&purse.relics, &mut rand.0) {
+                    purse.relics.push(relic.clone());
+                    commands.insert_resource(BossDrop(relic));
                 }
             }
-            Pope => {
-                minion_aoe_heals.push(3);
+            next_phase.set(GamePhase::Shop);
+            for &(id, amount) in &battle.grown {
+                // Summons don't outlive the fight, so only party members keep what they grew.
+                let Some(index) = battle.party.iter().position(|&member| member == id) else { continue; };
+                party[index].0.max_health += amount;
             }
-            Invincible => {
-                let target = army_entities[rand.gen_range(0..a_count)];
-                let mut army = armies.get_mut(target).unwrap();
-                commands.entity(target).despawn_recursive();
+            for (_, bounty) in party.iter_mut() {
+                *bounty += 1;
             }
-            General => {
-                minion_aoe_reductions.push(1);
-            }
-            _ => unimplemented!()
         }
-    }
-
-    for heal in minion_heals {
-        let target = rand.gen_range(0..m_count);
-        let mut minion = minions.get_mut(minion_entities[target]).unwrap();
-        if minion.2.health > minion.1.max_health {
-            continue;
-        } else if minion.1.max_health - minion.2.health < heal {
-            minion.2.health = minion.1.max_health;
-        } else {
-            minion.2.health += heal;
+        Outcome::Defeat => {
+            stats.record_fight(&battle, &battle.party);
+            next_phase.set(GamePhase::GameOver);
         }
+        Outcome::Ongoing => {}
     }
+}
 
-    for heal in minion_aoe_heals {
-        for mut minion in minions.iter_mut() {
-            if minion.2.health > minion.1.max_health {
-                continue;
-            } else if minion.2.health - minion.1.max_health < heal {
-                minion.2.health = minion.1.max_health;
-            } else {
-                minion.2.health += heal;
+fn shop_start(
+    mut commands: Commands,
+    shop: Res<Balance<Shop>>,
+    purse: Res<PartyPurse>,
+    drop: Option<Res<BossDrop>>,
+    rand: Res<Random>,
+    mut autosave: EventWriter<Autosave>,
+) {
+    autosave.send(Autosave { phase: SavedPhase::Shop, rng: rand.0.clone() });
+    let drop = drop.as_ref().map(|drop| &drop.0);
+    commands.spawn((
+        TextBundle::from_section(shop_text(&shop, &purse, drop), TextStyle { font_size: 30.0, ..default() })
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(20.0),
+                left: Val::Px(20.0),
+                ..default()
+            }),
+        ShopPanel,
+    ));
+}
+
+fn shop_tick(
+    mut decisions: EventReader<Decided>,
+    shop: Res<Balance<Shop>>,
+    mut purse: ResMut<PartyPurse>,
+    mut rand: ResMut<Random>,
+    mut recorder: Option<ResMut<Recording>>,
+    mut next_phase: ResMut<NextState<GamePhase>>,
+    mut panel: Query<&mut Text, With<ShopPanel>>,
+    drop: Option<Res<BossDrop>>,
+) {
+    let drop = drop.as_ref().map(|drop| &drop.0);
+    for &Decided(decision) in decisions.read() {
+        match decision {
+            Decision::Buy(index) => {
+                let Some(&offer) = shop.offers.get(index) else { continue; };
+                if !purse.buy(offer, &shop, &mut rand.0) { continue; }
+                for mut text in panel.iter_mut() {
+                    text.sections[0].value = shop_text(&shop, &purse, drop);
+                }
             }
+            Decision::Confirm => next_phase.set(GamePhase::Draft),
+            _ => continue,
         }
-    }
-    
-    for heal in minion_aoe_overheals {
-        for mut minion in minions.iter_mut() {
-            minion.2.health += heal;
+        if let Some(recorder) = recorder.as_mut() {
+            recorder.record(decision);
         }
+        if decision == Decision::Confirm {
+            break;
+        }
+    }
+}
+
+fn shop_end(
+    mut commands: Commands,
+    panel: Query<Entity, With<ShopPanel>>,
+) {
+    for entity in panel.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    commands.remove_resource::<BossDrop>();
+}
+
+fn shop_text(shop: &Shop, purse: &Purse, drop: Option<&Relic>) -> String {
+    let mut text = String::new();
+    if let Some(relic) = drop {
+        text += &format!("The boss dropped the {}!\n\n", relic.name);
     }
-    
-    for reduction in minion_reductions {
-        let target = rand.gen_range(0..m_count);
-        let mut minion = minions.get_mut(minion_entities[target]).unwrap();
-        if minion.2.countdown < reduction { continue }
-        minion.2.countdown -= reduction;
+    text += &format!("Gold: {}\n\n", purse.gold);
+    for (i, offer) in shop.offers.iter().enumerate() {
+        let ware = match offer.ware {
+            Ware::Reroll => format!("Draft reroll, press R in the draft (have {})", purse.rerolls),
+            Ware::ExtraPick => format!("Extra draft pick (have {})", purse.extra_picks),
+            Ware::Heal(amount) => format!("Heal the party by {amount} next fight (have {})", purse.heal),
+            Ware::Item => format!("A random item (have {} to equip)", purse.items.len()),
+            Ware::Relic => format!("A random relic (have {})", purse.relics.len()),
+        };
+        text += &format!("{}: {ware}, {} gold\n", (i + 1) % 10, offer.price);
     }
+    text += "\nPress a number to buy, or enter to move on to the draft";
+    text
+}
 
-    for reduction in minion_aoe_reductions {
-        for mut minion in minions.iter_mut() {
-            if minion.2.countdown < reduction { continue }
-            minion.2.countdown -= reduction;
+/// Despawns the units that died in the fight.
+fn despawn_dead(mut commands: Commands, mut died: EventReader<UnitDied>) {
+    for event in died.read() {
+        if let Some(entity) = commands.get_entity(event.unit) {
+            entity.despawn_recursive();
         }
     }
+}
+
+fn game_over_start(
+    mut commands: Commands,
+    party: Res<Party>,
+    stats: Res<RunStats>,
+    draft_settings: Res<DraftSettings>,
+    save_path: Option<Res<SavePath>>,
+) {
+    if let Some(path) = save_path {
+        SaveFile::delete(&path.0);
+    }
 
-    for attack in army_attacks {
-        if m_count == 0 {
-            println!("you lost!");
-            exit(0);
+    let text_style = TextStyle { font_size: 24.0, ..default() };
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                row_gap: Val::Px(8.0),
+                ..default()
+            },
+            background_color: Color::rgba(0.0, 0.0, 0.0, 0.8).into(),
+            ..default()
+        },
+        GameOverScreen,
+    )).with_children(|parent| {
+        parent.spawn(TextBundle::from_section("You lost!", TextStyle { font_size: 48.0, ..default() }));
+        parent.spawn(TextBundle::from_section(format!("Rounds survived: {}", stats.rounds), text_style.clone()));
+        parent.spawn(TextBundle::from_section(format!("Final power: {}", draft_settings.power), text_style.clone()));
+        for (i, (minion, bounty)) in party.iter().enumerate() {
+            let member = stats.member(i);
+            parent.spawn(TextBundle::from_section(
+                format!("{} (bounty {bounty}): {} damage, {} healing", minion.name, member.damage_dealt, member.healing_done),
+                text_style.clone(),
+            ));
         }
-        let target = rand.gen_range(0..m_count);
-        let mut minion = minions.get_mut(minion_entities[target]).unwrap();
-        if minion.2.health <= attack {
-            minion_entities.remove(target);
-            commands.entity(minion.0).despawn_recursive();
-            m_count -= 1;
-        } else {
-            minion.2.health -= attack;
+
+        parent.spawn(NodeBundle {
+            style: Style {
+                column_gap: Val::Px(16.0),
+                margin: UiRect::top(Val::Px(16.0)),
+                ..default()
+            },
+            ..default()
+        }).with_children(|row| {
+            for (button, label) in [(GameOverButton::NewRun, "New run"), (GameOverButton::Quit, "Quit")] {
+                row.spawn((
+                    ButtonBundle {
+                        style: Style {
+                            padding: UiRect::axes(Val::Px(16.0), Val::Px(8.0)),
+                            ..default()
+                        },
+                        background_color: Color::DARK_GRAY.into(),
+                        ..default()
+                    },
+                    button,
+                )).with_children(|button| {
+                    button.spawn(TextBundle::from_section(label, text_style.clone()));
+                });
+            }
+        });
+    });
+}
+
+fn game_over_tick(
+    buttons: Query<(&Interaction, &GameOverButton), Changed<Interaction>>,
+    mut party: ResMut<Party>,
+    mut stats: ResMut<RunStats>,
+    mut purse: ResMut<PartyPurse>,
+    mut draft_settings: ResMut<DraftSettings>,
+    mut rand: ResMut<Random>,
+    mut recorder: Option<ResMut<Recording>>,
+    mut next_phase: ResMut<NextState<GamePhase>>,
+    mut app_exit: EventWriter<AppExit>,
+) {
+    for (interaction, button) in buttons.iter() {
+        if *interaction != Interaction::Pressed { continue; }
+        match button {
+            GameOverButton::NewRun => {
+                // The new run's seed follows from the old one, so a session stays reproducible.
+                let seed = rand.gen();
+                println!("seed: {seed}");
+                rand.0 = ChaCha12Rng::seed_from_u64(seed);
+                party.clear();
+                *stats = RunStats::default();
+                *purse = PartyPurse::default();
+                draft_settings.power = draft_settings.start_power;
+                draft_settings.bonus = 0;
+                draft_settings.locked = None;
+                if let Some(recorder) = recorder.as_mut() {
+                    let run = ReplayFile { seed, power: draft_settings.power, decisions: vec![] };
+                    if let Err(err) = recorder.resume(run) {
+                        eprintln!("could not record the new run: {err}");
+                    }
+                }
+                next_phase.set(GamePhase::Draft);
+            }
+            GameOverButton::Quit => { app_exit.send(AppExit); }
         }
     }
-    println!("{0:?}, {1:?}", m_count, minion_entities.len());
+}
 
+fn game_over_end(
+    mut commands: Commands,
+    screen: Query<Entity, With<GameOverScreen>>,
+) {
+    for entity in screen.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
 }
 
-fn spawn_army(commands: &mut Commands, asset_server: &AssetServer, health: usize, damage: usize, countdown: usize) {
-    let warlord_helmet_image = asset_server.load("warlord-helmet.png");
+fn spawn_army(commands: &mut Commands, asset_server: &AssetServer, army: &BattleArmy, boss: bool) -> Entity {
+    let texture = asset_server.load(army.sprite.clone());
+    let (scale, color) = if boss {
+        (0.015, Color::rgb(0.5, 0.0, 0.3))
+    } else {
+        (0.01, Color::rgb(0.8, 0.0, 0.0))
+    };
     commands.spawn((
         SpriteBundle {
             transform: Transform {
-                scale: Vec3::new(0.01, 0.01, 0.0),
+                scale: Vec3::new(scale, scale, 0.0),
                 ..default()
             },
-            texture: warlord_helmet_image,
+            texture,
             sprite: Sprite {
-                color: Color::rgb(0.8, 0.0, 0.0),
+                color,
                 ..default()
             },
             ..default()
         },
         Army {
-            max_health: 10+health,
-            health: 10+health,
-            damage: 1+damage,
-            countdown: (5-countdown).max(1),
-            max_countdown: (5-countdown).max(1),
-        }
+            health: army.health,
+            max_health: army.max_health,
+            statuses: vec![],
+        },
+        BattleId(army.id),
+        Slot(army.id as usize),
     )).with_children(|parent| {
         parent.spawn((
             SpriteBundle {
@@ -880,39 +1655,22 @@ fn spawn_army(commands: &mut Commands, asset_server: &AssetServer, health: usize
             },
             HealthBar(),
         ));
-    });
-
-}
-
-fn generate_class(power: usize, rng: &mut StdRng) -> Class {
-    match rng.gen_range(0..power) {
-        0..=4 => { match rng.gen_range(0..4) {
-            0 => return Arcanist,
-            1 => return Cleric,
-            2 => return Warrior,
-            3 => return Scout,
-            _ => unreachable!()
-        } },
-        5..=8 => { match rng.gen_range(0..4) {
-            0 => return Pyromancer,
-            1 => return Priest,
-            2 => return Rogue,
-            3 => return Tactician,
-            _ => unreachable!()
-        } },
-        9..=12 => { match rng.gen_range(0..4) {
-            0 => return Necromancer,
-            1 => return Oracle,
-            2 => return Bulwark,
-            3 => return Sage,
-            _ => unreachable!()
-        } },
-        _ => { match rng.gen_range(0..4) {
-            0 => return Archmage,
-            1 => return Pope,
-            2 => return Invincible,
-            3 => return General,
-            _ => unreachable!()
-        } }
-    }
+        // An elite's affixes are shown in a row above it.
+        let offset = (army.affixes.len() as f32 - 1.0) / 2.0;
+        for (i, affix) in army.affixes.iter().enumerate() {
+            parent.spawn(SpriteBundle {
+                transform: Transform {
+                    translation: Vec3::new((i as f32 - offset) * 250.0, 4.0 / 0.01, 0.0),
+                    scale: Vec3::new(0.4, 0.4, 0.0),
+                    rotation: default(),
+                },
+                texture: asset_server.load(affix.icon.clone()),
+                sprite: Sprite {
+                    color: Color::rgb(1.0, 0.8, 0.0),
+                    ..default()
+                },
+                ..default()
+            });
+        }
+    }).id()
 }
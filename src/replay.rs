@@ -0,0 +1,229 @@
+use std::fs::File;
+use std::io::{Seek, Write};
+
+use serde::{Deserialize, Serialize};
+
+const HEADER: &str = "you-behind-it-must-leave replay";
+const VERSION: u32 = 2;
+
+/// A choice made by the player. Everything that happens in a run follows from the seed and these.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Decision {
+    /// Picked the draft card at this index, counted from the left.
+    Draft(usize),
+    /// Spent a reroll, or gold, on new draft cards.
+    Reroll,
+    /// Passed on the draft cards, keeping their power for the next draft. Only once the party has
+    /// three members.
+    Skip,
+    /// Locked the draft card at this index, so it is offered again with the next cards, or unlocked
+    /// it if it already was.
+    Lock(usize),
+    /// Bought what the shop offers at this index, counted from the top.
+    Buy(usize),
+    /// Discarded the party member at this index.
+    Discard(usize),
+    /// Swapped the party member at this index with the one in front of it.
+    Forward(usize),
+    /// Upgraded the party member at this index into the class its class upgrades into.
+    Upgrade(usize),
+    /// Merged the party member at this index with the first two other copies of it with as many
+    /// stars.
+    Merge(usize),
+    /// Equipped the party member at this index with the first item no one carries.
+    Equip(usize),
+    /// Pressed enter to leave the discard screen and start the fight, or to leave the shop.
+    Confirm,
+}
+
+/// The contents of a replay file.
+///
+/// The format is line based: a header with the format version, the seed and starting power, and
+/// then one decision per line, e.g.
+///
+/// ```text
+/// you-behind-it-must-leave replay 2
+/// seed 1234
+/// power 1
+/// draft 2
+/// lock 1
+/// reroll
+/// skip
+/// discard 0
+/// forward 2
+/// upgrade 1
+/// merge 0
+/// equip 2
+/// confirm
+/// buy 1
+/// confirm
+/// ```
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ReplayFile {
+    pub seed: u64,
+    pub power: usize,
+    pub decisions: Vec<Decision>,
+}
+
+impl ReplayFile {
+    pub fn load(path: &str) -> Result<ReplayFile, String> {
+        let contents = std::fs::read_to_string(path).map_err(|err| format!("could not read {path}: {err}"))?;
+        let mut lines = contents.lines().enumerate().map(|(i, line)| (i + 1, line.trim()));
+
+        let version = match lines.next().and_then(|(_, line)| line.strip_prefix(HEADER)) {
+            Some(version) => version.trim(),
+            None => return Err(format!("{path} is not a replay file")),
+        };
+        if version != VERSION.to_string() {
+            return Err(format!("{path} is a version {version} replay, only version {VERSION} is supported"));
+        }
+
+        let mut seed = None;
+        let mut power = None;
+        let mut decisions = vec![];
+        for (n, line) in lines {
+            let mut words = line.split_whitespace();
+            let bad_line = || format!("{path}:{n}: could not parse `{line}`");
+            match (words.next(), words.next()) {
+                (None, _) => {}
+                (Some("seed"), Some(value)) => seed = Some(value.parse().map_err(|_| bad_line())?),
+                (Some("power"), Some(value)) => power = Some(value.parse().map_err(|_| bad_line())?),
+                (Some("draft"), Some(index)) => decisions.push(Decision::Draft(index.parse().map_err(|_| bad_line())?)),
+                (Some("reroll"), None) => decisions.push(Decision::Reroll),
+                (Some("skip"), None) => decisions.push(Decision::Skip),
+                (Some("lock"), Some(index)) => decisions.push(Decision::Lock(index.parse().map_err(|_| bad_line())?)),
+                (Some("buy"), Some(index)) => decisions.push(Decision::Buy(index.parse().map_err(|_| bad_line())?)),
+                (Some("discard"), Some(index)) => decisions.push(Decision::Discard(index.parse().map_err(|_| bad_line())?)),
+                (Some("forward"), Some(index)) => decisions.push(Decision::Forward(index.parse().map_err(|_| bad_line())?)),
+                (Some("upgrade"), Some(index)) => decisions.push(Decision::Upgrade(index.parse().map_err(|_| bad_line())?)),
+                (Some("merge"), Some(index)) => decisions.push(Decision::Merge(index.parse().map_err(|_| bad_line())?)),
+                (Some("equip"), Some(index)) => decisions.push(Decision::Equip(index.parse().map_err(|_| bad_line())?)),
+                (Some("confirm"), None) => decisions.push(Decision::Confirm),
+                _ => return Err(bad_line()),
+            }
+        }
+
+        Ok(ReplayFile {
+            seed: seed.ok_or(format!("{path} has no seed"))?,
+            power: power.ok_or(format!("{path} has no starting power"))?,
+            decisions,
+        })
+    }
+}
+
+/// Writes every decision the player makes to a replay file as it happens, so the file is
+/// complete even if the game crashes.
+pub struct Recorder {
+    file: File,
+    run: ReplayFile,
+}
+
+impl Recorder {
+    pub fn create(path: &str, seed: u64, power: usize) -> std::io::Result<Recorder> {
+        let mut recorder = Recorder {
+            file: File::create(path)?,
+            run: ReplayFile { seed, power, decisions: vec![] },
+        };
+        recorder.write_run()?;
+        Ok(recorder)
+    }
+
+    /// Starts the replay file over for another run, such as one that is being continued.
+    pub fn resume(&mut self, run: ReplayFile) -> std::io::Result<()> {
+        self.run = run;
+        self.file.set_len(0)?;
+        self.file.rewind()?;
+        self.write_run()
+    }
+
+    fn write_run(&mut self) -> std::io::Result<()> {
+        writeln!(self.file, "{HEADER} {VERSION}")?;
+        writeln!(self.file, "seed {}", self.run.seed)?;
+        writeln!(self.file, "power {}", self.run.power)?;
+        for &decision in self.run.decisions.iter() {
+            write_decision(&mut self.file, decision)?;
+        }
+        Ok(())
+    }
+
+    pub fn record(&mut self, decision: Decision) {
+        self.run.decisions.push(decision);
+        if let Err(err) = write_decision(&mut self.file, decision) {
+            eprintln!("could not record decision: {err}");
+        }
+    }
+
+    /// The run recorded so far.
+    pub fn run(&self) -> &ReplayFile {
+        &self.run
+    }
+}
+
+fn write_decision(file: &mut File, decision: Decision) -> std::io::Result<()> {
+    match decision {
+        Decision::Draft(index) => writeln!(file, "draft {index}"),
+        Decision::Reroll => writeln!(file, "reroll"),
+        Decision::Skip => writeln!(file, "skip"),
+        Decision::Lock(index) => writeln!(file, "lock {index}"),
+        Decision::Buy(index) => writeln!(file, "buy {index}"),
+        Decision::Discard(index) => writeln!(file, "discard {index}"),
+        Decision::Forward(index) => writeln!(file, "forward {index}"),
+        Decision::Upgrade(index) => writeln!(file, "upgrade {index}"),
+        Decision::Merge(index) => writeln!(file, "merge {index}"),
+        Decision::Equip(index) => writeln!(file, "equip {index}"),
+        Decision::Confirm => writeln!(file, "confirm"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A file in the temporary directory that is deleted again when dropped.
+    struct TempFile(String);
+
+    impl TempFile {
+        fn new(name: &str) -> TempFile {
+            let path = std::env::temp_dir().join(format!("{name}-{}.replay", std::process::id()));
+            TempFile(path.to_string_lossy().into_owned())
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn reads_back_what_was_recorded() {
+        let file = TempFile::new("round-trip");
+        let decisions = [
+            Decision::Draft(2), Decision::Lock(1), Decision::Reroll, Decision::Skip, Decision::Discard(0),
+            Decision::Forward(2), Decision::Upgrade(1), Decision::Merge(0), Decision::Equip(2),
+            Decision::Confirm, Decision::Buy(1), Decision::Confirm,
+        ];
+        let mut recorder = Recorder::create(&file.0, 1234, 3).unwrap();
+        for decision in decisions {
+            recorder.record(decision);
+        }
+
+        let replay = ReplayFile::load(&file.0).unwrap();
+        assert_eq!(replay.seed, 1234);
+        assert_eq!(replay.power, 3);
+        assert_eq!(replay.decisions, decisions);
+    }
+
+    #[test]
+    fn rejects_other_versions_and_files() {
+        let file = TempFile::new("old-version");
+        std::fs::write(&file.0, format!("{HEADER} 1\nseed 1\npower 1\ndraft 0\n")).unwrap();
+        let err = ReplayFile::load(&file.0).err().unwrap();
+        assert!(err.ends_with("is a version 1 replay, only version 2 is supported"), "{err}");
+
+        let file = TempFile::new("not-a-replay");
+        std::fs::write(&file.0, "seed 1\npower 1\n").unwrap();
+        let err = ReplayFile::load(&file.0).err().unwrap();
+        assert!(err.ends_with("is not a replay file"), "{err}");
+    }
+}
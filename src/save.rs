@@ -0,0 +1,95 @@
+use std::io::ErrorKind;
+
+use bevy::asset::ron;
+use bevy::prelude::*;
+use rand_chacha::ChaCha12Rng;
+use serde::{Deserialize, Serialize};
+
+use you_behind_it_must_leave::class::MinionType;
+use you_behind_it_must_leave::replay::ReplayFile;
+use you_behind_it_must_leave::shop::Purse;
+
+use crate::stats::RunStats;
+
+const VERSION: u32 = 2;
+
+/// The phase a run was saved in. A phase is saved as it starts, so continuing starts it over.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub enum SavedPhase {
+    Draft { power_left: usize },
+    Discard,
+    Fight,
+    Shop,
+}
+
+/// A run in progress, written out as RON so it can be continued after the game is closed.
+#[derive(Resource, Serialize, Deserialize)]
+pub struct SaveFile {
+    version: u32,
+    /// Each party member with its bounty.
+    pub party: Vec<(MinionType, usize)>,
+    pub power: usize,
+    pub phase: SavedPhase,
+    /// The random number generator as it was when the phase started, so that continuing plays the
+    /// phase out the same way.
+    pub rng: ChaCha12Rng,
+    /// The run so far, if it was being recorded, so that the recording can carry on.
+    pub replay: Option<ReplayFile>,
+    #[serde(default)]
+    pub stats: RunStats,
+    #[serde(default)]
+    pub purse: Purse,
+    /// Power skipped in earlier drafts, for the next one.
+    #[serde(default)]
+    pub bonus: usize,
+    /// The locked draft card and where it is offered.
+    #[serde(default)]
+    pub locked: Option<(usize, MinionType)>,
+}
+
+impl SaveFile {
+    pub fn new(
+        party: Vec<(MinionType, usize)>,
+        power: usize,
+        phase: SavedPhase,
+        rng: ChaCha12Rng,
+        replay: Option<ReplayFile>,
+        stats: RunStats,
+        purse: Purse,
+        bonus: usize,
+        locked: Option<(usize, MinionType)>,
+    ) -> SaveFile {
+        SaveFile { version: VERSION, party, power, phase, rng, replay, stats, purse, bonus, locked }
+    }
+
+    /// Reads the save at `path`, or returns `None` if there is none.
+    pub fn load(path: &str) -> Result<Option<SaveFile>, String> {
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(format!("could not read {path}: {err}")),
+        };
+        let save: SaveFile = ron::de::from_str(&contents).map_err(|err| format!("could not parse {path}: {err}"))?;
+        if save.version != VERSION {
+            return Err(format!("{path} is a version {} save, only version {VERSION} is supported", save.version));
+        }
+        Ok(Some(save))
+    }
+
+    /// Writes the save to a temporary file first, so that a crash while saving doesn't lose the
+    /// previous save.
+    pub fn write(&self, path: &str) -> Result<(), String> {
+        let contents = ron::ser::to_string_pretty(self, default()).map_err(|err| err.to_string())?;
+        let temporary = format!("{path}.tmp");
+        std::fs::write(&temporary, contents).map_err(|err| format!("could not write {temporary}: {err}"))?;
+        std::fs::rename(&temporary, path).map_err(|err| format!("could not write {path}: {err}"))
+    }
+
+    pub fn delete(path: &str) {
+        match std::fs::remove_file(path) {
+            Ok(()) => {}
+            Err(err) if err.kind() == ErrorKind::NotFound => {}
+            Err(err) => eprintln!("could not delete {path}: {err}"),
+        }
+    }
+}
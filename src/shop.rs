@@ -0,0 +1,104 @@
+use rand::Rng;
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+
+use crate::army::Scale;
+use crate::item::{Item, Relic};
+
+/// The gold for winning a fight: `base`, plus `power` scaled from the draft power, plus
+/// `per_survivor` for every party member still standing at the end. Summons don't count.
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub struct Reward {
+    pub base: usize,
+    pub power: Scale,
+    pub per_survivor: usize,
+}
+
+impl Reward {
+    pub fn gold(&self, power: usize, survivors: usize) -> usize {
+        self.base + self.power.apply(power) + self.per_survivor * survivors
+    }
+}
+
+/// Something the shop sells.
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Ware {
+    /// New cards in place of the ones a draft offers, once.
+    Reroll,
+    /// One more card in the next draft, drawn at the full draft power.
+    ExtraPick,
+    /// Every minion starts the next fight with this much more health, past its max.
+    Heal(usize),
+    /// One of the `items`, at random, to equip a minion with.
+    Item,
+    /// One of the `relics` the party doesn't have yet, at random.
+    Relic,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub struct Offer {
+    pub ware: Ware,
+    pub price: usize,
+}
+
+/// What winning pays and what the shop between fights sells. Read from the `shop.ron` asset file.
+#[derive(Deserialize, Clone, Debug)]
+pub struct Shop {
+    pub reward: Reward,
+    /// The gold for rerolling the draft cards without a reroll bought here.
+    pub reroll: usize,
+    pub offers: Vec<Offer>,
+    pub items: Vec<Item>,
+    /// Sold here, and dropped by beaten bosses.
+    pub relics: Vec<Relic>,
+}
+
+impl Shop {
+    /// A relic the party doesn't have yet, if there are any left.
+    pub fn relic(&self, owned: &[Relic], rng: &mut impl Rng) -> Option<Relic> {
+        let left: Vec<_> = self.relics.iter()
+            .filter(|relic| !owned.iter().any(|owned| owned.name == relic.name))
+            .collect();
+        left.choose(rng).map(|&relic| relic.clone())
+    }
+}
+
+/// The party's gold, what it has bought but not used yet and its relics.
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+pub struct Purse {
+    pub gold: usize,
+    pub rerolls: usize,
+    pub extra_picks: usize,
+    /// Extra health for every minion in the next fight.
+    pub heal: usize,
+    /// Items no minion carries.
+    #[serde(default)]
+    pub items: Vec<Item>,
+    #[serde(default)]
+    pub relics: Vec<Relic>,
+}
+
+impl Purse {
+    /// Buys what is on offer from `shop` if there is enough gold and something left to get,
+    /// returning whether it was bought.
+    pub fn buy(&mut self, offer: Offer, shop: &Shop, rng: &mut impl Rng) -> bool {
+        if self.gold < offer.price {
+            return false;
+        }
+        match offer.ware {
+            Ware::Reroll => self.rerolls += 1,
+            Ware::ExtraPick => self.extra_picks += 1,
+            Ware::Heal(amount) => self.heal += amount,
+            Ware::Item => {
+                let Some(item) = shop.items.choose(rng) else { return false; };
+                self.items.push(item.clone());
+            }
+            Ware::Relic => {
+                let Some(relic) = shop.relic(&self.relics, rng) else { return false; };
+                self.relics.push(relic);
+            }
+        }
+        self.gold -= offer.price;
+        true
+    }
+}
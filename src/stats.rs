@@ -0,0 +1,69 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use you_behind_it_must_leave::battle::{Battle, UnitId};
+
+/// How the run has gone so far, for the summary once it is over.
+#[derive(Resource, Serialize, Deserialize, Default, Clone)]
+pub struct RunStats {
+    /// Fights won.
+    pub rounds: usize,
+    /// What each party member has done, in party order.
+    members: Vec<MemberStats>,
+}
+
+/// What a party member and everything it summoned have done over the run.
+#[derive(Serialize, Deserialize, Default, Clone, Copy)]
+pub struct MemberStats {
+    pub damage_dealt: usize,
+    pub healing_done: usize,
+}
+
+impl RunStats {
+    pub fn drafted(&mut self) {
+        self.members.push(MemberStats::default());
+    }
+
+    pub fn discarded(&mut self, index: usize) {
+        if index < self.members.len() {
+            self.members.remove(index);
+        }
+    }
+
+    /// Follows a party member swapping places with the one in front of it.
+    pub fn moved_forward(&mut self, index: usize) {
+        if index > 0 && index < self.members.len() {
+            self.members.swap(index - 1, index);
+        }
+    }
+
+    /// Follows the party member at `from` being merged into the one at `into`, which takes over
+    /// what it has done.
+    pub fn merged(&mut self, from: usize, into: usize) {
+        if from == into || from >= self.members.len() || into >= self.members.len() {
+            return;
+        }
+        let merged = self.members.remove(from);
+        let into = if from < into { into - 1 } else { into };
+        self.members[into].damage_dealt += merged.damage_dealt;
+        self.members[into].healing_done += merged.healing_done;
+    }
+
+    pub fn member(&self, index: usize) -> MemberStats {
+        self.members.get(index).copied().unwrap_or_default()
+    }
+
+    /// Adds up what happened in a fight. `party` is the unit of each party member in the battle, in
+    /// party order.
+    pub fn record_fight(&mut self, battle: &Battle, party: &[UnitId]) {
+        if self.members.len() < party.len() {
+            self.members.resize(party.len(), MemberStats::default());
+        }
+        for tally in battle.tallies.iter() {
+            let credited = battle.credited_to(tally.id);
+            let Some(index) = party.iter().position(|&id| id == credited) else { continue; };
+            self.members[index].damage_dealt += tally.damage_dealt;
+            self.members[index].healing_done += tally.healing_done;
+        }
+    }
+}
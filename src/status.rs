@@ -0,0 +1,153 @@
+use serde::{Deserialize, Serialize};
+
+use crate::battle::UnitId;
+
+/// Something that sticks to a unit for a number of ticks.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum StatusKind {
+    /// Loses `amount` health at the start of every tick, shields or not.
+    Burn,
+    /// Loses `amount` health at the start of every tick, shields or not, and can't be healed.
+    Poison,
+    /// Its countdown doesn't run.
+    Stun,
+    /// The next `amount` damage it takes is absorbed.
+    Shield,
+    /// Deals `amount` less damage.
+    Weakness,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct Status {
+    pub kind: StatusKind,
+    #[serde(default)]
+    pub amount: usize,
+    /// How many ticks it lasts.
+    pub ticks: usize,
+}
+
+/// A status on a unit in a battle, and who gave it.
+#[derive(Clone, Copy, Debug)]
+pub struct ActiveStatus {
+    pub status: Status,
+    pub source: UnitId,
+    /// Whether it was given during the current tick, so that it has not been in effect yet.
+    pub fresh: bool,
+}
+
+/// The statuses on a unit, at most one of each kind.
+#[derive(Clone, Default, Debug)]
+pub struct Statuses(Vec<ActiveStatus>);
+
+impl Statuses {
+    /// The amount of the status, if the unit has it.
+    pub fn get(&self, kind: StatusKind) -> Option<usize> {
+        self.0.iter().find(|active| active.status.kind == kind).map(|active| active.status.amount)
+    }
+
+    pub fn has(&self, kind: StatusKind) -> bool {
+        self.get(kind).is_some()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &ActiveStatus> {
+        self.0.iter()
+    }
+
+    /// Gives the unit a status. If it already has one of the kind, it keeps the larger amount and
+    /// the longer duration of the two.
+    pub fn apply(&mut self, status: Status, source: UnitId) {
+        if status.ticks == 0 {
+            return;
+        }
+        match self.0.iter_mut().find(|active| active.status.kind == status.kind) {
+            Some(active) => {
+                active.status.amount = active.status.amount.max(status.amount);
+                if status.ticks >= active.status.ticks {
+                    active.status.ticks = status.ticks;
+                    active.fresh = true;
+                }
+                active.source = source;
+            }
+            None => self.0.push(ActiveStatus { status, source, fresh: true }),
+        }
+    }
+
+    /// Lets a shield take what it can of `damage`, returning what gets through.
+    pub fn absorb(&mut self, damage: usize) -> usize {
+        let Some(index) = self.0.iter().position(|active| active.status.kind == StatusKind::Shield) else {
+            return damage;
+        };
+        let shield = &mut self.0[index].status;
+        let absorbed = shield.amount.min(damage);
+        shield.amount -= absorbed;
+        if shield.amount == 0 {
+            self.0.remove(index);
+        }
+        damage - absorbed
+    }
+
+    /// Puts every status the unit has into effect for a new tick. Returns the damage the unit
+    /// takes at its start and who gave it the status that deals it.
+    pub fn start_tick(&mut self) -> Vec<(UnitId, usize)> {
+        for active in self.0.iter_mut() {
+            active.fresh = false;
+        }
+        self.0.iter()
+            .filter(|active| matches!(active.status.kind, StatusKind::Burn | StatusKind::Poison))
+            .filter(|active| active.status.amount > 0)
+            .map(|active| (active.source, active.status.amount))
+            .collect()
+    }
+
+    /// Counts down every status that was in effect for the tick, dropping the ones that run out.
+    /// The ones given during the tick start counting down after the next, so that a status lasts
+    /// as many ticks as it says.
+    pub fn end_tick(&mut self) {
+        self.0.retain_mut(|active| {
+            if !active.fresh {
+                active.status.ticks -= 1;
+            }
+            active.status.ticks > 0
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(kind: StatusKind, amount: usize, ticks: usize) -> Status {
+        Status { kind, amount, ticks }
+    }
+
+    #[test]
+    fn shield_absorbs_damage_until_it_breaks() {
+        let mut statuses = Statuses::default();
+        statuses.apply(status(StatusKind::Shield, 3, 5), 0);
+
+        assert_eq!(statuses.absorb(2), 0);
+        assert_eq!(statuses.get(StatusKind::Shield), Some(1));
+        assert_eq!(statuses.absorb(4), 3);
+        assert!(!statuses.has(StatusKind::Shield));
+        assert_eq!(statuses.absorb(4), 4);
+    }
+
+    #[test]
+    fn hurts_for_as_many_ticks_as_it_lasts() {
+        let mut statuses = Statuses::default();
+        statuses.apply(status(StatusKind::Burn, 2, 1), 7);
+        statuses.apply(status(StatusKind::Poison, 1, 2), 8);
+        statuses.apply(status(StatusKind::Stun, 0, 2), 9);
+        // Given during a tick, so they only start counting down after the next.
+        statuses.end_tick();
+        assert_eq!(statuses.iter().count(), 3);
+
+        assert_eq!(statuses.start_tick(), vec![(7, 2), (8, 1)]);
+        statuses.end_tick();
+        assert!(!statuses.has(StatusKind::Burn));
+        assert!(statuses.has(StatusKind::Stun));
+        assert_eq!(statuses.start_tick(), vec![(8, 1)]);
+        statuses.end_tick();
+        assert_eq!(statuses.iter().count(), 0);
+    }
+}
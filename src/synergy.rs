@@ -0,0 +1,89 @@
+use serde::{Deserialize, Serialize};
+
+use crate::class::{MinionType, Type};
+
+/// What a synergy or a relic makes the whole party better at.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Bonus {
+    /// More damage from every damaging ability.
+    Damage,
+    /// More health from every healing ability.
+    Healing,
+    /// More max health.
+    Health,
+    /// Shorter countdowns, down to a single tick.
+    Countdown,
+}
+
+/// A synergy level: with at least `count` minions of the type in the party, the bonus is `amount`.
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub struct Level {
+    pub count: usize,
+    pub amount: usize,
+}
+
+/// A party-wide bonus for fielding several minions of the same type.
+#[derive(Deserialize, Clone, Debug)]
+pub struct Synergy {
+    #[serde(rename = "type")]
+    pub of: Type,
+    pub bonus: Bonus,
+    pub levels: Vec<Level>,
+}
+
+/// Read from the `synergies.ron` asset file.
+#[derive(Deserialize, Clone, Debug)]
+pub struct Synergies {
+    pub synergies: Vec<Synergy>,
+}
+
+/// How a synergy stands for a party.
+#[derive(Clone, Debug)]
+pub struct SynergyStatus<'a> {
+    pub synergy: &'a Synergy,
+    /// Minions of the type in the party. Minions with two types count for both.
+    pub count: usize,
+    /// The highest level reached, if any.
+    pub level: Option<Level>,
+}
+
+/// The bonuses a party gets from all of its synergies together.
+#[derive(Default, Clone, Copy, Debug)]
+pub struct Bonuses {
+    pub damage: usize,
+    pub healing: usize,
+    pub health: usize,
+    pub countdown: usize,
+}
+
+impl Synergies {
+    /// How each synergy stands for a party of minions and their bounties.
+    pub fn status(&self, party: &[(MinionType, usize)]) -> Vec<SynergyStatus<'_>> {
+        self.synergies.iter().map(|synergy| {
+            let count = party.iter().filter(|(minion, _)| minion.types.contains(&synergy.of)).count();
+            let level = synergy.levels.iter().filter(|level| count >= level.count).max_by_key(|level| level.count).copied();
+            SynergyStatus { synergy, count, level }
+        }).collect()
+    }
+
+    pub fn bonuses(&self, party: &[(MinionType, usize)]) -> Bonuses {
+        let mut bonuses = Bonuses::default();
+        for status in self.status(party) {
+            let Some(level) = status.level else { continue; };
+            bonuses.add(status.synergy.bonus, level.amount);
+        }
+        bonuses
+    }
+}
+
+impl Bonuses {
+    pub fn add(&mut self, bonus: Bonus, amount: usize) {
+        let total = match bonus {
+            Bonus::Damage => &mut self.damage,
+            Bonus::Healing => &mut self.healing,
+            Bonus::Health => &mut self.health,
+            Bonus::Countdown => &mut self.countdown,
+        };
+        *total += amount;
+    }
+}
@@ -0,0 +1,117 @@
+use rand::Rng;
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+
+use crate::battle::{BattleArmy, BattleMinion};
+
+/// How a unit picks which of the units it can reach its ability or attack goes to.
+#[derive(Serialize, Deserialize, Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Targeting {
+    #[default]
+    Random,
+    LowestHealth,
+    HighestHealth,
+    /// The one that hits hardest. For minions, the one with the largest ability amount.
+    HighestDamage,
+    /// The one whose countdown runs out first.
+    SoonestCountdown,
+    /// The one missing the most health.
+    MostInjured,
+}
+
+/// What targeting looks at in a unit that could be picked.
+#[derive(Clone, Copy, Debug)]
+pub struct Candidate {
+    /// Where the unit is in its side's list.
+    pub index: usize,
+    pub health: usize,
+    pub missing_health: usize,
+    pub damage: usize,
+    pub countdown: usize,
+}
+
+impl Candidate {
+    pub fn minion(index: usize, minion: &BattleMinion) -> Candidate {
+        Candidate {
+            index,
+            health: minion.health,
+            missing_health: minion.kind.max_health.saturating_sub(minion.health),
+            damage: minion.kind.amount,
+            countdown: minion.countdown,
+        }
+    }
+
+    pub fn army(index: usize, army: &BattleArmy) -> Candidate {
+        Candidate {
+            index,
+            health: army.health,
+            missing_health: army.max_health.saturating_sub(army.health),
+            damage: army.damage,
+            countdown: army.countdown,
+        }
+    }
+}
+
+impl Targeting {
+    /// Picks the index of the candidate that fits best, breaking ties at random, or `None` if
+    /// there are no candidates.
+    pub fn pick(self, candidates: &[Candidate], rng: &mut impl Rng) -> Option<usize> {
+        let key = |candidate: &Candidate| -> i64 {
+            match self {
+                Targeting::Random => 0,
+                Targeting::LowestHealth => candidate.health as i64,
+                Targeting::HighestHealth => -(candidate.health as i64),
+                Targeting::HighestDamage => -(candidate.damage as i64),
+                Targeting::SoonestCountdown => candidate.countdown as i64,
+                Targeting::MostInjured => -(candidate.missing_health as i64),
+            }
+        };
+        let best = candidates.iter().map(key).min()?;
+        let ties: Vec<_> = candidates.iter().filter(|candidate| key(candidate) == best).collect();
+        ties.choose(rng).map(|candidate| candidate.index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha12Rng;
+
+    use super::*;
+
+    fn candidate(index: usize, health: usize, missing_health: usize, damage: usize, countdown: usize) -> Candidate {
+        Candidate { index, health, missing_health, damage, countdown }
+    }
+
+    #[test]
+    fn picks_the_candidate_that_fits_best() {
+        let candidates = [
+            candidate(0, 5, 0, 1, 1),
+            candidate(1, 2, 4, 2, 3),
+            candidate(2, 8, 1, 3, 2),
+        ];
+        let mut rng = ChaCha12Rng::seed_from_u64(0);
+
+        assert_eq!(Targeting::LowestHealth.pick(&candidates, &mut rng), Some(1));
+        assert_eq!(Targeting::HighestHealth.pick(&candidates, &mut rng), Some(2));
+        assert_eq!(Targeting::HighestDamage.pick(&candidates, &mut rng), Some(2));
+        assert_eq!(Targeting::SoonestCountdown.pick(&candidates, &mut rng), Some(0));
+        assert_eq!(Targeting::MostInjured.pick(&candidates, &mut rng), Some(1));
+        assert_eq!(Targeting::Random.pick(&[], &mut rng), None);
+    }
+
+    #[test]
+    fn breaks_ties_between_the_best_candidates_only() {
+        // The indices are where the units are in their side's list, not where they are here.
+        let candidates = [
+            candidate(3, 2, 0, 0, 0),
+            candidate(5, 9, 0, 0, 0),
+            candidate(7, 2, 0, 0, 0),
+        ];
+        let mut rng = ChaCha12Rng::seed_from_u64(0);
+
+        let picks: Vec<_> = (0..50).map(|_| Targeting::LowestHealth.pick(&candidates, &mut rng)).collect();
+        assert!(picks.iter().all(|&pick| pick == Some(3) || pick == Some(7)));
+        assert!(picks.contains(&Some(3)) && picks.contains(&Some(7)));
+    }
+}